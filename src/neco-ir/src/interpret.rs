@@ -0,0 +1,333 @@
+// runs a program. calls are kept on a stack of frames, not on the stack of the interpreter.
+
+use crate::{BinOp, BlockId, FunctionId, Inst, Local, Program, Terminator, Value};
+use std::fmt;
+
+// the maximum depth of calls
+pub const MAX_FRAMES: usize = 10000;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum RuntimeError {
+    UnknownFunction(FunctionId),
+    WrongArgumentCount { expected: usize, found: usize },
+    InvalidOperands,
+    DivisionByZero,
+    StackOverflow,
+    Unreachable,
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RuntimeError::UnknownFunction(id) => write!(f, "unknown function #{}", id.0),
+            RuntimeError::WrongArgumentCount { expected, found } => {
+                write!(f, "expected {} arguments, found {}", expected, found)
+            }
+            RuntimeError::InvalidOperands => write!(f, "invalid operands"),
+            RuntimeError::DivisionByZero => write!(f, "division by zero"),
+            RuntimeError::StackOverflow => write!(f, "stack overflow"),
+            RuntimeError::Unreachable => write!(f, "reached unreachable code"),
+        }
+    }
+}
+
+struct Frame {
+    function: FunctionId,
+    locals: Vec<Value>,
+    block: BlockId,
+    inst: usize,
+    // where the caller receives the result
+    dest: Option<Local>,
+}
+
+fn new_frame(
+    program: &Program,
+    function: FunctionId,
+    args: Vec<Value>,
+    dest: Option<Local>,
+) -> Result<Frame, RuntimeError> {
+    let f = program
+        .function(function)
+        .ok_or(RuntimeError::UnknownFunction(function))?;
+    if args.len() != f.params {
+        return Err(RuntimeError::WrongArgumentCount {
+            expected: f.params,
+            found: args.len(),
+        });
+    }
+    let mut locals = args;
+    locals.resize(f.locals, Value::Unit);
+    Ok(Frame {
+        function,
+        locals,
+        block: BlockId(0),
+        inst: 0,
+        dest,
+    })
+}
+
+// sign-extend the lowest bits of v
+fn wrap(v: i64, bits: usize) -> i64 {
+    if bits == 0 || bits >= 64 {
+        return v;
+    }
+    let shift = 64 - bits;
+    (v << shift) >> shift
+}
+
+fn binary(op: BinOp, bits: usize, lhs: &Value, rhs: &Value) -> Result<Value, RuntimeError> {
+    match op {
+        BinOp::Eq => return Ok(Value::Bool(lhs == rhs)),
+        BinOp::Ne => return Ok(Value::Bool(lhs != rhs)),
+        _ => {}
+    }
+    let (lhs, rhs) = match (lhs, rhs) {
+        (Value::Int(lhs), Value::Int(rhs)) => (*lhs, *rhs),
+        _ => return Err(RuntimeError::InvalidOperands),
+    };
+    let v = match op {
+        BinOp::Add => lhs.wrapping_add(rhs),
+        BinOp::Sub => lhs.wrapping_sub(rhs),
+        BinOp::Mul => lhs.wrapping_mul(rhs),
+        BinOp::Div => {
+            if rhs == 0 {
+                return Err(RuntimeError::DivisionByZero);
+            }
+            lhs.wrapping_div(rhs)
+        }
+        BinOp::Lt => return Ok(Value::Bool(lhs < rhs)),
+        BinOp::Le => return Ok(Value::Bool(lhs <= rhs)),
+        BinOp::Gt => return Ok(Value::Bool(lhs > rhs)),
+        BinOp::Ge => return Ok(Value::Bool(lhs >= rhs)),
+        BinOp::Eq | BinOp::Ne => unreachable!(),
+    };
+    Ok(Value::Int(wrap(v, bits)))
+}
+
+pub fn run(
+    program: &Program,
+    function: FunctionId,
+    args: Vec<Value>,
+) -> Result<Value, RuntimeError> {
+    let mut frames = vec![new_frame(program, function, args, None)?];
+    loop {
+        let frame = frames.last_mut().unwrap();
+        let f = &program.functions[frame.function.0];
+        let block = &f.blocks[frame.block.0];
+        if let Some(inst) = block.insts.get(frame.inst) {
+            frame.inst += 1;
+            match inst {
+                Inst::Const { dest, value } => frame.locals[dest.0] = value.clone(),
+                Inst::Copy { dest, src } => frame.locals[dest.0] = frame.locals[src.0].clone(),
+                Inst::Binary {
+                    dest,
+                    op,
+                    bits,
+                    lhs,
+                    rhs,
+                } => {
+                    let v = binary(*op, *bits, &frame.locals[lhs.0], &frame.locals[rhs.0])?;
+                    frame.locals[dest.0] = v;
+                }
+                Inst::Call {
+                    dest,
+                    function,
+                    args,
+                } => {
+                    let args = args.iter().map(|arg| frame.locals[arg.0].clone()).collect();
+                    if frames.len() >= MAX_FRAMES {
+                        return Err(RuntimeError::StackOverflow);
+                    }
+                    frames.push(new_frame(program, *function, args, Some(*dest))?);
+                }
+            }
+            continue;
+        }
+        match &block.terminator {
+            Terminator::Return(local) => {
+                let v = frame.locals[local.0].clone();
+                let dest = frame.dest;
+                frames.pop();
+                match (frames.last_mut(), dest) {
+                    (Some(caller), Some(dest)) => caller.locals[dest.0] = v,
+                    _ => return Ok(v),
+                }
+            }
+            Terminator::Jump(block) => {
+                frame.block = *block;
+                frame.inst = 0;
+            }
+            Terminator::Branch { cond, then, else_ } => {
+                frame.block = match frame.locals[cond.0] {
+                    Value::Bool(true) => *then,
+                    Value::Bool(false) => *else_,
+                    _ => return Err(RuntimeError::InvalidOperands),
+                };
+                frame.inst = 0;
+            }
+            Terminator::Unreachable => return Err(RuntimeError::Unreachable),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FunctionBuilder;
+
+    // fn double(x) { x + x }
+    // fn main() { double(21) }
+    fn program(bits: usize) -> Program {
+        let mut program = Program::new();
+        let mut double = FunctionBuilder::new("double", 1);
+        let x = double.param(0);
+        let res = double.new_local();
+        double.push(Inst::Binary {
+            dest: res,
+            op: BinOp::Add,
+            bits,
+            lhs: x,
+            rhs: x,
+        });
+        double.terminate(Terminator::Return(res));
+        let double = program.add_function(double.finish());
+
+        let mut main = FunctionBuilder::new("main", 0);
+        let arg = main.new_local();
+        let res = main.new_local();
+        main.push(Inst::Const {
+            dest: arg,
+            value: Value::Int(100),
+        });
+        // jump to another block and call
+        let next = main.new_block();
+        main.terminate(Terminator::Jump(next));
+        main.switch_to(next);
+        main.push(Inst::Call {
+            dest: res,
+            function: double,
+            args: vec![arg],
+        });
+        main.terminate(Terminator::Return(res));
+        program.add_function(main.finish());
+        program
+    }
+
+    #[test]
+    fn test_run() {
+        let program = program(64);
+        let main = program.function_by_name("main").unwrap();
+        assert_eq!(run(&program, main, vec![]), Ok(Value::Int(200)));
+        // 200 is -56 in 8 bits
+        let program = self::program(8);
+        assert_eq!(run(&program, main, vec![]), Ok(Value::Int(-56)));
+
+        let double = program.function_by_name("double").unwrap();
+        assert_eq!(
+            run(&program, double, vec![]),
+            Err(RuntimeError::WrongArgumentCount {
+                expected: 1,
+                found: 0
+            })
+        );
+    }
+
+    #[test]
+    fn test_run_branch() {
+        // fn sum(n) { let s = 0; while 0 < n { s = s + n; n = n - 1; } s }
+        let mut program = Program::new();
+        let mut sum = FunctionBuilder::new("sum", 1);
+        let n = sum.param(0);
+        let s = sum.new_local();
+        let zero = sum.new_local();
+        let one = sum.new_local();
+        let cond = sum.new_local();
+        sum.push(Inst::Const {
+            dest: s,
+            value: Value::Int(0),
+        });
+        sum.push(Inst::Const {
+            dest: zero,
+            value: Value::Int(0),
+        });
+        sum.push(Inst::Const {
+            dest: one,
+            value: Value::Int(1),
+        });
+        let header = sum.new_block();
+        let body = sum.new_block();
+        let exit = sum.new_block();
+        sum.terminate(Terminator::Jump(header));
+        sum.switch_to(header);
+        sum.push(Inst::Binary {
+            dest: cond,
+            op: BinOp::Lt,
+            bits: 64,
+            lhs: zero,
+            rhs: n,
+        });
+        sum.terminate(Terminator::Branch {
+            cond,
+            then: body,
+            else_: exit,
+        });
+        sum.switch_to(body);
+        for (dest, op, rhs) in [(s, BinOp::Add, n), (n, BinOp::Sub, one)] {
+            sum.push(Inst::Binary {
+                dest,
+                op,
+                bits: 64,
+                lhs: dest,
+                rhs,
+            });
+        }
+        sum.terminate(Terminator::Jump(header));
+        sum.switch_to(exit);
+        sum.terminate(Terminator::Return(s));
+        let sum = program.add_function(sum.finish());
+        assert_eq!(
+            run(&program, sum, vec![Value::Int(100)]),
+            Ok(Value::Int(5050))
+        );
+        assert_eq!(run(&program, sum, vec![Value::Int(-1)]), Ok(Value::Int(0)));
+
+        assert_eq!(
+            binary(BinOp::Eq, 0, &Value::Unit, &Value::Unit),
+            Ok(Value::Bool(true))
+        );
+        assert_eq!(
+            binary(BinOp::Lt, 64, &Value::Bool(false), &Value::Bool(true)),
+            Err(RuntimeError::InvalidOperands)
+        );
+    }
+
+    #[test]
+    fn test_run_errors() {
+        // fn f() { f() }
+        let mut program = Program::new();
+        let mut f = FunctionBuilder::new("f", 0);
+        let res = f.new_local();
+        f.push(Inst::Call {
+            dest: res,
+            function: FunctionId(0),
+            args: vec![],
+        });
+        f.terminate(Terminator::Return(res));
+        let f = program.add_function(f.finish());
+        assert_eq!(run(&program, f, vec![]), Err(RuntimeError::StackOverflow));
+
+        // a block without a terminator
+        let mut program = Program::new();
+        let g = program.add_function(FunctionBuilder::new("g", 0).finish());
+        assert_eq!(run(&program, g, vec![]), Err(RuntimeError::Unreachable));
+
+        assert_eq!(
+            binary(BinOp::Div, 32, &Value::Int(1), &Value::Int(0)),
+            Err(RuntimeError::DivisionByZero)
+        );
+        assert_eq!(
+            binary(BinOp::Div, 32, &Value::Int(-2147483648), &Value::Int(-1)),
+            Ok(Value::Int(-2147483648))
+        );
+    }
+}
@@ -1 +1,197 @@
+// an intermediate representation which can be run by interpret.
+//
+// a function is a list of basic blocks. instructions read and write the locals of the function,
+// and each block ends with a terminator, which returns or jumps to another block. loops and
+// conditionals are branches between blocks.
 
+pub mod interpret;
+
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FunctionId(pub usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BlockId(pub usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Local(pub usize);
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Value {
+    Unit,
+    Bool(bool),
+    Int(i64),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Unit => write!(f, "()"),
+            Value::Bool(v) => write!(f, "{}", v),
+            Value::Int(v) => write!(f, "{}", v),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Inst {
+    Const {
+        dest: Local,
+        value: Value,
+    },
+    Copy {
+        dest: Local,
+        src: Local,
+    },
+    // signed integer arithmetic, which wraps around at the width of bits.
+    // comparisons give a Bool, and Eq and Ne compare any values.
+    Binary {
+        dest: Local,
+        op: BinOp,
+        bits: usize,
+        lhs: Local,
+        rhs: Local,
+    },
+    Call {
+        dest: Local,
+        function: FunctionId,
+        args: Vec<Local>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Terminator {
+    Return(Local),
+    Jump(BlockId),
+    // to then if cond is true, otherwise to else_
+    Branch {
+        cond: Local,
+        then: BlockId,
+        else_: BlockId,
+    },
+    // the end of a block which is never reached, like the code after `return`
+    Unreachable,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BasicBlock {
+    pub insts: Vec<Inst>,
+    pub terminator: Terminator,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Function {
+    pub name: String,
+    // the arguments are in the first locals
+    pub params: usize,
+    pub locals: usize,
+    // the first block is the entry
+    pub blocks: Vec<BasicBlock>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Program {
+    pub functions: Vec<Function>,
+}
+
+impl Program {
+    pub fn new() -> Program {
+        Program::default()
+    }
+    pub fn add_function(&mut self, function: Function) -> FunctionId {
+        self.functions.push(function);
+        FunctionId(self.functions.len() - 1)
+    }
+    pub fn function(&self, id: FunctionId) -> Option<&Function> {
+        self.functions.get(id.0)
+    }
+    pub fn function_by_name(&self, name: &str) -> Option<FunctionId> {
+        self.functions
+            .iter()
+            .position(|function| function.name == name)
+            .map(FunctionId)
+    }
+}
+
+// builds a function block by block. instructions are added to the current block.
+pub struct FunctionBuilder {
+    name: String,
+    params: usize,
+    locals: usize,
+    blocks: Vec<(Vec<Inst>, Option<Terminator>)>,
+    current: BlockId,
+}
+
+impl FunctionBuilder {
+    pub fn new(name: &str, params: usize) -> FunctionBuilder {
+        FunctionBuilder {
+            name: name.to_string(),
+            params,
+            locals: params,
+            blocks: vec![(vec![], None)],
+            current: BlockId(0),
+        }
+    }
+    pub fn param(&self, i: usize) -> Local {
+        assert!(i < self.params);
+        Local(i)
+    }
+    pub fn new_local(&mut self) -> Local {
+        self.locals += 1;
+        Local(self.locals - 1)
+    }
+    pub fn new_block(&mut self) -> BlockId {
+        self.blocks.push((vec![], None));
+        BlockId(self.blocks.len() - 1)
+    }
+    pub fn current(&self) -> BlockId {
+        self.current
+    }
+    pub fn switch_to(&mut self, block: BlockId) {
+        self.current = block;
+    }
+    pub fn push(&mut self, inst: Inst) {
+        self.blocks[self.current.0].0.push(inst);
+    }
+    // a block keeps its first terminator
+    pub fn terminate(&mut self, terminator: Terminator) {
+        let block = &mut self.blocks[self.current.0];
+        if block.1.is_none() {
+            block.1 = Some(terminator);
+        }
+    }
+    pub fn is_terminated(&self) -> bool {
+        self.blocks[self.current.0].1.is_some()
+    }
+    // blocks without a terminator are unreachable
+    pub fn finish(self) -> Function {
+        Function {
+            name: self.name,
+            params: self.params,
+            locals: self.locals,
+            blocks: self
+                .blocks
+                .into_iter()
+                .map(|(insts, terminator)| BasicBlock {
+                    insts,
+                    terminator: terminator.unwrap_or(Terminator::Unreachable),
+                })
+                .collect(),
+        }
+    }
+}
@@ -1,231 +1,1443 @@
+mod pattern;
+
 use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
 use quote::*;
-use syn::{
-    parse_macro_input, Arm, Data, DeriveInput, ExprMatch, ExprStruct, FieldValue, Fields,
-    ImplItemMethod, Stmt, Type,
-};
+use syn::{parse_macro_input, spanned::Spanned, Data, DeriveInput, Field, Fields, Type};
 
-#[proc_macro_derive(SyntaxTree, attributes(TokenSet))]
-pub fn derive_syntax_tree(input: TokenStream) -> TokenStream {
-    let input = parse_macro_input!(input as DeriveInput);
+fn wrap_memo(block: TokenStream2) -> TokenStream2 {
+    quote! {
+        tokens.memoize(|tokens| { #block })
+    }
+}
+
+// the field named `id`, or the field of type SyntaxTreeId in a tuple
+fn is_id_field(field: &Field) -> bool {
+    match &field.ident {
+        Some(ident) => ident == "id",
+        None => match &field.ty {
+            Type::Path(type_path) => type_path
+                .path
+                .segments
+                .last()
+                .is_some_and(|segment| segment.ident == "SyntaxTreeId"),
+            _ => false,
+        },
+    }
+}
+
+fn field_var(field: &Field, i: usize) -> syn::Ident {
+    match &field.ident {
+        Some(ident) => ident.clone(),
+        None => format_ident!("field_{}", i),
+    }
+}
+
+#[derive(Default)]
+struct FieldAttributes {
+    token: Option<syn::LitStr>,
+    lookahead: bool,
+    not: bool,
+    cut: bool,
+    skip: bool,
+}
+
+impl FieldAttributes {
+    fn is_empty(&self) -> bool {
+        self.token.is_none() && !self.lookahead && !self.not && !self.cut && !self.skip
+    }
+}
+
+fn parse_field_attributes(field: &Field) -> syn::Result<FieldAttributes> {
+    let mut res = FieldAttributes::default();
+    for attr in &field.attrs {
+        let flag = if attr.path.is_ident("token") {
+            if res.token.is_some() {
+                return Err(syn::Error::new_spanned(
+                    attr,
+                    "duplicate `#[token(...)]` attribute",
+                ));
+            }
+            res.token = Some(attr.parse_args::<syn::LitStr>()?);
+            continue;
+        } else if attr.path.is_ident("lookahead") {
+            &mut res.lookahead
+        } else if attr.path.is_ident("not") {
+            &mut res.not
+        } else if attr.path.is_ident("cut") {
+            &mut res.cut
+        } else if attr.path.is_ident("skip") {
+            &mut res.skip
+        } else {
+            continue;
+        };
+        if !attr.tokens.is_empty() {
+            return Err(syn::Error::new_spanned(
+                &attr.tokens,
+                "this attribute takes no arguments",
+            ));
+        }
+        *flag = true;
+    }
+    if res.lookahead && res.not {
+        return Err(syn::Error::new_spanned(
+            field,
+            "`#[lookahead]` and `#[not]` cannot be used together",
+        ));
+    }
+    if res.skip && (res.token.is_some() || res.lookahead || res.not || res.cut) {
+        return Err(syn::Error::new_spanned(
+            field,
+            "`#[skip]` cannot be used with other parser attributes",
+        ));
+    }
+    if is_id_field(field) && !res.is_empty() {
+        return Err(syn::Error::new_spanned(
+            field,
+            "parser attributes cannot be used on the id field",
+        ));
+    }
+    Ok(res)
+}
+
+// T of PhantomData<T>
+fn phantom_data_arg(ty: &Type) -> Option<&Type> {
+    let segment = match ty {
+        Type::Path(type_path) => type_path.path.segments.last()?,
+        _ => return None,
+    };
+    if segment.ident != "PhantomData" {
+        return None;
+    }
+    match &segment.arguments {
+        syn::PathArguments::AngleBracketed(args) if args.args.len() == 1 => match &args.args[0] {
+            syn::GenericArgument::Type(ty) => Some(ty),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+// statements which parse the fields in order, and the expression which builds the value.
+// the statements return from the enclosing function on failure, and ids are taken after all fields are parsed.
+// after a `#[cut]` field, failures are returned as errors.
+fn parse_sequence(
+    fields: &Fields,
+    path: TokenStream2,
+) -> syn::Result<(Vec<TokenStream2>, TokenStream2)> {
+    let mut stmts = vec![];
+    let mut id_stmts = vec![];
+    let mut values = vec![];
+    let mut cut = false;
+    for (i, field) in fields.iter().enumerate() {
+        let var = field_var(field, i);
+        let attrs = parse_field_attributes(field)?;
+        values.push(var.clone());
+        if is_id_field(field) {
+            id_stmts.push(quote! {
+                let #var = tokens.gen_id();
+            });
+            continue;
+        }
+        if attrs.skip {
+            stmts.push(quote! {
+                let #var = Default::default();
+            });
+            continue;
+        }
+        let fail = if cut {
+            quote! {
+                return neco_syn::ParserResult::Err(e);
+            }
+        } else {
+            quote! {
+                tokens.set_i(initial_i);
+                return neco_syn::ParserResult::Fail(e);
+            }
+        };
+        let ty = if attrs.not {
+            match phantom_data_arg(&field.ty) {
+                Some(ty) => ty,
+                None => {
+                    return Err(syn::Error::new_spanned(
+                        &field.ty,
+                        "`#[not]` fields must have type `PhantomData<T>`",
+                    ))
+                }
+            }
+        } else {
+            &field.ty
+        };
+        let is_match = match &attrs.token {
+            Some(text) => quote! {
+                neco_syn::Token::text(&t).as_deref() == Some(#text)
+            },
+            None => quote! { true },
+        };
+        if attrs.not {
+            let expected = match &attrs.token {
+                Some(text) => quote! { concat!("not ", #text) },
+                None => quote! { concat!("not ", stringify!(#ty)) },
+            };
+            stmts.push(quote! {
+                let #var = {
+                    let start_i = tokens.get_i();
+                    let matched = match tokens.parse::<#ty>() {
+                        neco_syn::ParserResult::Ok(t) => #is_match,
+                        neco_syn::ParserResult::Fail(_) => false,
+                        neco_syn::ParserResult::Err(e) => {
+                            return neco_syn::ParserResult::Err(e);
+                        }
+                    };
+                    tokens.set_i(start_i);
+                    if matched {
+                        let e = tokens.error(#expected);
+                        #fail
+                    }
+                    std::marker::PhantomData
+                };
+            });
+        } else {
+            let check = match &attrs.token {
+                Some(text) => quote! {
+                    if !(#is_match) {
+                        tokens.set_i(start_i);
+                        let e = tokens.error(#text);
+                        #fail
+                    }
+                },
+                None => quote! {},
+            };
+            let rewind = if attrs.lookahead {
+                quote! { tokens.set_i(start_i); }
+            } else {
+                quote! {}
+            };
+            stmts.push(quote! {
+                let #var = {
+                    let start_i = tokens.get_i();
+                    let t = match tokens.parse::<#ty>() {
+                        neco_syn::ParserResult::Ok(t) => t,
+                        neco_syn::ParserResult::Fail(e) => {
+                            #fail
+                        }
+                        neco_syn::ParserResult::Err(e) => {
+                            return neco_syn::ParserResult::Err(e);
+                        }
+                    };
+                    #check
+                    #rewind
+                    t
+                };
+            });
+        }
+        cut |= attrs.cut;
+    }
+    stmts.extend(id_stmts);
+    let construct = match fields {
+        Fields::Named(_) => quote! { #path { #(#values),* } },
+        Fields::Unnamed(_) => quote! { #path ( #(#values),* ) },
+        Fields::Unit => quote! { #path },
+    };
+    Ok((stmts, construct))
+}
+
+struct Attributes {
+    token_set: Type,
+    recover: Option<Recover>,
+    memo: bool,
+}
+
+// #[recover(Sync)] skips past Sync, #[recover(Sync, stop = Stop)] gives up at Stop on the way,
+// and #[recover(before = Start)] skips to the next Start
+struct Recover {
+    // the call of a skip method of Tokens
+    skip: TokenStream2,
+}
+
+impl syn::parse::Parse for Recover {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        if input.peek(syn::Ident) && input.peek2(syn::Token![=]) {
+            let key: syn::Ident = input.parse()?;
+            if key != "before" {
+                return Err(syn::Error::new(
+                    key.span(),
+                    "expected a type or `before = ...`",
+                ));
+            }
+            input.parse::<syn::Token![=]>()?;
+            let start: Type = input.parse()?;
+            return Ok(Recover {
+                skip: quote! { tokens.skip_to::<#start>() },
+            });
+        }
+        let sync: Type = input.parse()?;
+        if input.is_empty() {
+            return Ok(Recover {
+                skip: quote! { tokens.skip_until::<#sync>() },
+            });
+        }
+        input.parse::<syn::Token![,]>()?;
+        let key: syn::Ident = input.parse()?;
+        if key != "stop" {
+            return Err(syn::Error::new(key.span(), "expected `stop = ...`"));
+        }
+        input.parse::<syn::Token![=]>()?;
+        let stop: Type = input.parse()?;
+        Ok(Recover {
+            skip: quote! { tokens.skip_until_stop::<#sync, #stop>() },
+        })
+    }
+}
+
+// other attributes such as #[doc] or #[allow] are left to the compiler
+fn parse_attributes(input: &DeriveInput) -> syn::Result<Attributes> {
     let mut token_set = None;
-    assert!(input.attrs.len() == 1);
+    let mut recover = None;
+    let mut memo = false;
     for attr in &input.attrs {
-        let t = attr.tokens.clone();
-        let t = t.into();
-        let t = parse_macro_input!(t as Type);
-        if let Type::Paren(type_paren) = t {
-            token_set = Some(type_paren.elem.as_ref().clone());
-        } else {
-            panic!("not paren?");
+        if attr.path.is_ident("TokenSet") {
+            if token_set.is_some() {
+                return Err(syn::Error::new_spanned(
+                    attr,
+                    "duplicate `#[TokenSet(...)]` attribute",
+                ));
+            }
+            token_set = Some(attr.parse_args::<Type>()?);
+        } else if attr.path.is_ident("recover") {
+            if recover.is_some() {
+                return Err(syn::Error::new_spanned(
+                    attr,
+                    "duplicate `#[recover(...)]` attribute",
+                ));
+            }
+            recover = Some(attr.parse_args::<Recover>()?);
+        } else if attr.path.is_ident("memo") {
+            if !attr.tokens.is_empty() {
+                return Err(syn::Error::new_spanned(
+                    &attr.tokens,
+                    "`#[memo]` takes no arguments",
+                ));
+            }
+            memo = true;
+        }
+    }
+    let token_set = match token_set {
+        Some(token_set) => token_set,
+        None => {
+            return Err(syn::Error::new(
+                input.ident.span(),
+                "missing `#[TokenSet(...)]` attribute",
+            ))
         }
+    };
+    Ok(Attributes {
+        token_set,
+        recover,
+        memo,
+    })
+}
+
+fn into_token_stream(res: syn::Result<TokenStream2>) -> TokenStream {
+    match res {
+        Ok(res) => res.into(),
+        Err(e) => e.to_compile_error().into(),
     }
-    let token_set = token_set.unwrap();
-    let data = input.data;
+}
+
+#[proc_macro_derive(
+    SyntaxTree,
+    attributes(TokenSet, name, recover, memo, token, lookahead, not, cut, skip)
+)]
+pub fn derive_syntax_tree(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    into_token_stream(expand_syntax_tree(input))
+}
+
+fn expand_syntax_tree(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let Attributes {
+        token_set,
+        recover,
+        memo,
+    } = parse_attributes(&input)?;
+    let recover_fn = match recover {
+        Some(Recover { skip }) => quote! {
+            fn recover(tokens: &mut neco_syn::Tokens<#token_set>) -> bool {
+                #skip
+            }
+        },
+        None => quote! {},
+    };
+    let walk = expand_walk(&input)?;
+    let span_fn = expand_span(&input, &token_set)?;
+    let shift_fn = expand_shift(&input, &token_set)?;
+    let name = parse_name(&input.attrs, &input.ident)?;
     let ident = input.ident;
-    let res = match data {
+    let res = match input.data {
         Data::Struct(data_struct) => {
-            let base = TokenStream::from(quote! {
-                fn parse(tokens: &mut neco_syn::Tokens<#token_set>) -> neco_syn::ParserResult<#ident> {
+            let fields = &data_struct.fields;
+            let id = match fields
+                .iter()
+                .enumerate()
+                .find(|(_, field)| is_id_field(field))
+            {
+                Some((
+                    _,
+                    Field {
+                        ident: Some(id), ..
+                    },
+                )) => quote! { self.#id },
+                Some((i, _)) => {
+                    let i = syn::Index::from(i);
+                    quote! { self.#i }
                 }
-            });
-            let mut base = parse_macro_input!(base as ImplItemMethod);
-
-            let stmt = TokenStream::from(quote! {
+                None => {
+                    let message = match fields {
+                        Fields::Unnamed(_) => "expected a field of type `SyntaxTreeId`",
+                        _ => "expected a field `id: SyntaxTreeId`",
+                    };
+                    return Err(syn::Error::new(data_struct.struct_token.span, message));
+                }
+            };
+            let (stmts, construct) = parse_sequence(fields, quote! { #ident })?;
+            let mut body = quote! {
                 let initial_i = tokens.get_i();
-            });
-            base.block.stmts.push(parse_macro_input!(stmt as Stmt));
-            if let Fields::Named(fields_named) = data_struct.fields {
-                for item in &fields_named.named {
-                    let ident = item.ident.clone().unwrap();
-                    if ident.to_string() == "id" {
-                        continue;
-                    }
-                    let ty = item.ty.clone();
-                    let stmt = TokenStream::from(quote! {
-                        let #ident = if let neco_syn::ParserResult::Ok(t) = tokens.parse::<#ty>() {
-                            t
-                        } else {
-                            tokens.set_i(initial_i);
-                            return neco_syn::ParserResult::Fail;
-                        };
-                    });
-                    base.block.stmts.push(parse_macro_input!(stmt as Stmt));
+                #(#stmts)*
+                neco_syn::ParserResult::Ok(#construct)
+            };
+            if memo {
+                body = wrap_memo(body);
+            }
+            quote! {
+                impl neco_syn::SyntaxTree<#token_set> for #ident {
+                    const NAME: &'static str = #name;
+                    fn parse(tokens: &mut neco_syn::Tokens<#token_set>) -> neco_syn::ParserResult<#ident> {
+                        #body
+                    }
+                    #recover_fn
+                    fn id(&self) -> neco_syn::SyntaxTreeId {
+                        #id
+                    }
+                    #span_fn
+                    #shift_fn
                 }
-                let res = TokenStream::from(quote! {
-                    #ident {
+            }
+        }
+        Data::Enum(data_enum) => {
+            let mut alternatives = vec![];
+            let mut id_arms = vec![];
+            for variant in &data_enum.variants {
+                let variant_ident = &variant.ident;
+                let fields = &variant.fields;
+                if fields.is_empty() {
+                    return Err(syn::Error::new(
+                        variant.span(),
+                        "unit variants are not supported",
+                    ));
+                }
+                let single = match fields {
+                    Fields::Unnamed(fields_unnamed) if fields_unnamed.unnamed.len() == 1 => {
+                        let field = &fields_unnamed.unnamed[0];
+                        !is_id_field(field) && parse_field_attributes(field)?.is_empty()
                     }
-                });
-                let mut res = parse_macro_input!(res as ExprStruct);
-                let field = TokenStream::from(quote! {
-                    id: neco_syn::gen_next_syntax_tree_id()
-                });
-                res.fields.push(parse_macro_input!(field as FieldValue));
-                for item in &fields_named.named {
-                    let ident = item.ident.clone().unwrap();
-                    if ident.to_string() == "id" {
-                        continue;
-                    }
-                    let field = TokenStream::from(quote! {
-                        #ident
+                    _ => false,
+                };
+                if single {
+                    let ty = &fields.iter().next().unwrap().ty;
+                    alternatives.push(quote! {
+                        match tokens.parse::<#ty>() {
+                            neco_syn::ParserResult::Ok(t) => neco_syn::ParserResult::Ok(#ident::#variant_ident(t)),
+                            neco_syn::ParserResult::Fail(e) => neco_syn::ParserResult::Fail(e),
+                            neco_syn::ParserResult::Err(e) => neco_syn::ParserResult::Err(e),
+                        }
                     });
-                    res.fields.push(parse_macro_input!(field as FieldValue));
+                    id_arms.push(quote! {
+                        #ident::#variant_ident(t) => t.id(),
+                    });
+                    continue;
                 }
-                let stmt = TokenStream::from(quote! {
-                    let res = neco_syn::ParserResult::Ok(#res);
+                let (stmts, construct) = parse_sequence(fields, quote! { #ident::#variant_ident })?;
+                alternatives.push(quote! {
+                    (|tokens: &mut neco_syn::Tokens<#token_set>| -> neco_syn::ParserResult<#ident> {
+                        let initial_i = tokens.get_i();
+                        #(#stmts)*
+                        neco_syn::ParserResult::Ok(#construct)
+                    })(tokens)
                 });
-                base.block.stmts.push(parse_macro_input!(stmt as Stmt));
-                let stmt = TokenStream::from(quote! {
-                    return res;
+                // a sequence has an id of its own, not the id of one of its fields
+                let (i, field) = match fields
+                    .iter()
+                    .enumerate()
+                    .find(|(_, field)| is_id_field(field))
+                {
+                    Some(field) => field,
+                    None => {
+                        let message = match fields {
+                            Fields::Unnamed(_) => "expected a field of type `SyntaxTreeId`",
+                            _ => "expected a field `id: SyntaxTreeId`",
+                        };
+                        return Err(syn::Error::new(variant.span(), message));
+                    }
+                };
+                let var = field_var(field, i);
+                let pattern = match fields {
+                    Fields::Named(_) => quote! { #ident::#variant_ident { #var, .. } },
+                    _ => {
+                        let vars = (0..fields.len()).map(|j| {
+                            if j == i {
+                                quote! { #var }
+                            } else {
+                                quote! { _ }
+                            }
+                        });
+                        quote! { #ident::#variant_ident( #(#vars),* ) }
+                    }
+                };
+                id_arms.push(quote! {
+                    #pattern => *#var,
                 });
-                base.block.stmts.push(parse_macro_input!(stmt as Stmt));
-            } else {
-                panic!("not named field");
+            }
+            let mut body = quote! {
+                let initial_i = tokens.get_i();
+                let mut error: Option<neco_syn::ParseError> = None;
+                #(
+                    match #alternatives {
+                        neco_syn::ParserResult::Ok(t) => {
+                            return neco_syn::ParserResult::Ok(t);
+                        }
+                        neco_syn::ParserResult::Fail(e) => {
+                            tokens.set_i(initial_i);
+                            error = Some(match error.take() {
+                                Some(error) => error.merge(e),
+                                None => e,
+                            });
+                        }
+                        neco_syn::ParserResult::Err(e) => {
+                            return neco_syn::ParserResult::Err(e);
+                        }
+                    };
+                )*
+                neco_syn::ParserResult::Fail(match error {
+                    Some(error) => error,
+                    None => tokens.error(#name),
+                })
+            };
+            if memo {
+                body = wrap_memo(body);
             }
             quote! {
                 impl neco_syn::SyntaxTree<#token_set> for #ident {
-                    #base
+                    const NAME: &'static str = #name;
+                    fn parse(tokens: &mut neco_syn::Tokens<#token_set>) -> neco_syn::ParserResult<#ident> {
+                        #body
+                    }
+                    #recover_fn
                     fn id(&self) -> neco_syn::SyntaxTreeId {
-                        self.id
+                        match self {
+                            #(#id_arms)*
+                        }
                     }
+                    #span_fn
+                    #shift_fn
                 }
             }
         }
-        Data::Enum(data_enum) => {
-            let base = TokenStream::from(quote! {
-                fn parse(tokens: &mut neco_syn::Tokens<#token_set>) -> neco_syn::ParserResult<#ident> {
+        Data::Union(data_union) => {
+            return Err(syn::Error::new(
+                data_union.union_token.span,
+                "unions are not supported",
+            ));
+        }
+    };
+    Ok(quote! {
+        #res
+        #walk
+    })
+}
+
+// the fields which hold children: not the id, #[skip] or #[not]
+fn walked_fields(fields: &Fields) -> syn::Result<Vec<(usize, &Field)>> {
+    let mut res = vec![];
+    for (i, field) in fields.iter().enumerate() {
+        let attrs = parse_field_attributes(field)?;
+        if !is_id_field(field) && !attrs.skip && !attrs.not {
+            res.push((i, field));
+        }
+    }
+    Ok(res)
+}
+
+// the fields which were consumed: the walked fields except #[lookahead]
+fn consumed_fields(fields: &Fields) -> syn::Result<Vec<(usize, &Field)>> {
+    let mut res = vec![];
+    for (i, field) in walked_fields(fields)? {
+        if !parse_field_attributes(field)?.lookahead {
+            res.push((i, field));
+        }
+    }
+    Ok(res)
+}
+
+// a pattern of the variant which binds the given fields
+fn variant_pattern(
+    ident: &syn::Ident,
+    variant: &syn::Variant,
+    fields: &[(usize, &Field)],
+) -> (TokenStream2, Vec<syn::Ident>) {
+    let variant_ident = &variant.ident;
+    let vars: Vec<_> = fields
+        .iter()
+        .map(|(i, field)| field_var(field, *i))
+        .collect();
+    let pattern = match &variant.fields {
+        Fields::Named(_) => quote! { #ident::#variant_ident { #(#vars,)* .. } },
+        Fields::Unnamed(fields_unnamed) => {
+            let vars = (0..fields_unnamed.unnamed.len()).map(|j| {
+                match fields.iter().position(|(i, _)| *i == j) {
+                    Some(k) => {
+                        let var = &vars[k];
+                        quote! { #var }
+                    }
+                    None => quote! { _ },
                 }
             });
-            let mut base = parse_macro_input!(base as ImplItemMethod);
+            quote! { #ident::#variant_ident( #(#vars),* ) }
+        }
+        Fields::Unit => quote! { #ident::#variant_ident },
+    };
+    (pattern, vars)
+}
 
-            let stmt = TokenStream::from(quote! {
-                let initial_i = tokens.get_i();
-            });
-            base.block.stmts.push(parse_macro_input!(stmt as Stmt));
-            for item in &data_enum.variants {
-                let ident2 = item.ident.clone();
-                if let Fields::Unnamed(fields_unnamed) = &item.fields {
-                    let first_ty = fields_unnamed.unnamed.iter().next().unwrap().clone().ty;
-                    let stmt = TokenStream::from(quote! {
-                        if let neco_syn::ParserResult::Ok(t) = tokens.parse::<#first_ty>() {
-                            return neco_syn::ParserResult::Ok(#ident::#ident2(t));
-                        } else {
-                            tokens.set_i(initial_i);
-                        };
-                    });
-                    base.block.stmts.push(parse_macro_input!(stmt as Stmt));
-                } else {
-                    panic!("not Fields::Unnamed");
+// the span covering the consumed fields
+fn expand_span(input: &DeriveInput, token_set: &Type) -> syn::Result<TokenStream2> {
+    let body = match &input.data {
+        Data::Struct(data_struct) => {
+            let members = consumed_fields(&data_struct.fields)?
+                .into_iter()
+                .map(|(i, field)| match &field.ident {
+                    Some(field_ident) => quote! { #field_ident },
+                    None => {
+                        let i = syn::Index::from(i);
+                        quote! { #i }
+                    }
+                });
+            quote! {
+                neco_syn::Span::cover(&[
+                    #(neco_syn::SyntaxTree::<#token_set>::span(&self.#members)),*
+                ])
+            }
+        }
+        Data::Enum(data_enum) => {
+            let ident = &input.ident;
+            let mut arms = vec![];
+            for variant in &data_enum.variants {
+                let fields = consumed_fields(&variant.fields)?;
+                let (pattern, vars) = variant_pattern(ident, variant, &fields);
+                arms.push(quote! {
+                    #pattern => neco_syn::Span::cover(&[
+                        #(neco_syn::SyntaxTree::<#token_set>::span(#vars)),*
+                    ]),
+                });
+            }
+            quote! {
+                match self {
+                    #(#arms)*
                 }
             }
-            let stmt = TokenStream::from(quote! {
-                return neco_syn::ParserResult::Fail;
-            });
-            base.block.stmts.push(parse_macro_input!(stmt as Stmt));
+        }
+        Data::Union(_) => return Ok(quote! {}),
+    };
+    Ok(quote! {
+        fn span(&self) -> neco_syn::Span {
+            #body
+        }
+    })
+}
+
+// shift all the children, including #[lookahead] ones
+fn expand_shift(input: &DeriveInput, token_set: &Type) -> syn::Result<TokenStream2> {
+    let body = match &input.data {
+        Data::Struct(data_struct) => {
+            let members = walked_fields(&data_struct.fields)?
+                .into_iter()
+                .map(|(i, field)| match &field.ident {
+                    Some(field_ident) => quote! { #field_ident },
+                    None => {
+                        let i = syn::Index::from(i);
+                        quote! { #i }
+                    }
+                });
+            quote! {
+                #(neco_syn::SyntaxTree::<#token_set>::shift(&mut self.#members, delta);)*
+            }
+        }
+        Data::Enum(data_enum) => {
+            let ident = &input.ident;
+            let mut arms = vec![];
+            for variant in &data_enum.variants {
+                let fields = walked_fields(&variant.fields)?;
+                let (pattern, vars) = variant_pattern(ident, variant, &fields);
+                arms.push(quote! {
+                    #pattern => {
+                        #(neco_syn::SyntaxTree::<#token_set>::shift(#vars, delta);)*
+                    }
+                });
+            }
+            quote! {
+                match self {
+                    #(#arms)*
+                }
+            }
+        }
+        Data::Union(_) => return Ok(quote! {}),
+    };
+    Ok(quote! {
+        #[allow(unused_variables)]
+        fn shift(&mut self, delta: isize) {
+            #body
+        }
+    })
+}
 
-            let base2 = TokenStream::from(quote! {
+// Walk and WalkMut, which accept the children in order
+fn expand_walk(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let ident = &input.ident;
+    let mut tys = vec![];
+    let mut walk = vec![];
+    let mut walk_mut = vec![];
+    match &input.data {
+        Data::Struct(data_struct) => {
+            for (i, field) in walked_fields(&data_struct.fields)? {
+                let member = match &field.ident {
+                    Some(field_ident) => quote! { #field_ident },
+                    None => {
+                        let i = syn::Index::from(i);
+                        quote! { #i }
+                    }
+                };
+                tys.push(&field.ty);
+                walk.push(quote! {
+                    neco_syn::visit::Accept::accept(&self.#member, v);
+                });
+                walk_mut.push(quote! {
+                    neco_syn::visit::AcceptMut::accept_mut(&mut self.#member, v);
+                });
+            }
+        }
+        Data::Enum(data_enum) => {
+            let mut arms = vec![];
+            let mut arms_mut = vec![];
+            for variant in &data_enum.variants {
+                let fields = walked_fields(&variant.fields)?;
+                tys.extend(fields.iter().map(|(_, field)| &field.ty));
+                let (pattern, vars) = variant_pattern(ident, variant, &fields);
+                arms.push(quote! {
+                    #pattern => {
+                        #(neco_syn::visit::Accept::accept(#vars, v);)*
+                    }
+                });
+                arms_mut.push(quote! {
+                    #pattern => {
+                        #(neco_syn::visit::AcceptMut::accept_mut(#vars, v);)*
+                    }
+                });
+            }
+            walk.push(quote! {
                 match self {
+                    #(#arms)*
                 }
             });
-            let mut base2 = parse_macro_input!(base2 as ExprMatch);
-            for item in &data_enum.variants {
-                let ident2 = item.ident.clone();
-                if let Fields::Unnamed(fields_unnamed) = &item.fields {
-                    let first_ty = fields_unnamed.unnamed.iter().next().unwrap().clone().ty;
-                    let arm = TokenStream::from(quote! {
-                        #ident::#ident2(t) => t.id(),
-                    });
-                    base2.arms.push(parse_macro_input!(arm as Arm));
-                } else {
-                    panic!("not Fields::Unnamed");
+            walk_mut.push(quote! {
+                match self {
+                    #(#arms_mut)*
                 }
+            });
+        }
+        Data::Union(_) => return Ok(quote! {}),
+    }
+    Ok(quote! {
+        impl<V: ?Sized> neco_syn::visit::Walk<V> for #ident
+        where
+            #(#tys: neco_syn::visit::Accept<V>,)*
+        {
+            #[allow(unused_variables)]
+            fn walk(&self, v: &mut V) {
+                #(#walk)*
+            }
+        }
+        impl<V: ?Sized> neco_syn::visit::WalkMut<V> for #ident
+        where
+            #(#tys: neco_syn::visit::AcceptMut<V>,)*
+        {
+            #[allow(unused_variables)]
+            fn walk_mut(&mut self, v: &mut V) {
+                #(#walk_mut)*
             }
+        }
+    })
+}
 
-            quote! {
-                impl neco_syn::SyntaxTree<#token_set> for #ident {
-                    #base
-                    fn id(&self) -> neco_syn::SyntaxTreeId {
-                        #base2
-                    }
+// Walk and WalkMut of a node without children
+fn expand_leaf_walk(ident: &syn::Ident) -> TokenStream2 {
+    quote! {
+        impl<V: ?Sized> neco_syn::visit::Walk<V> for #ident {
+            fn walk(&self, _v: &mut V) {}
+        }
+        impl<V: ?Sized> neco_syn::visit::WalkMut<V> for #ident {
+            fn walk_mut(&mut self, _v: &mut V) {}
+        }
+    }
+}
+
+#[proc_macro_derive(TokenSet, attributes(trivia))]
+pub fn derive_token_set(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    into_token_stream(expand_token_set(input))
+}
+
+fn expand_token_set(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let ident = input.ident;
+    let data_enum = match input.data {
+        Data::Enum(data_enum) => data_enum,
+        Data::Struct(data_struct) => {
+            return Err(syn::Error::new(
+                data_struct.struct_token.span,
+                "`TokenSet` can only be derived for enums",
+            ))
+        }
+        Data::Union(data_union) => {
+            return Err(syn::Error::new(
+                data_union.union_token.span,
+                "`TokenSet` can only be derived for enums",
+            ))
+        }
+    };
+    let mut tokens = vec![];
+    for variant in &data_enum.variants {
+        match &variant.fields {
+            Fields::Unnamed(fields_unnamed) if fields_unnamed.unnamed.len() == 1 => {
+                tokens.push((&variant.ident, &fields_unnamed.unnamed[0].ty));
+            }
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    variant,
+                    "expected a variant with a single token, like `Ident(TokenIdent)`",
+                ))
+            }
+        }
+    }
+    let mut trivia = vec![];
+    for variant in &data_enum.variants {
+        for attr in &variant.attrs {
+            if attr.path.is_ident("trivia") {
+                if !attr.tokens.is_empty() {
+                    return Err(syn::Error::new_spanned(
+                        &attr.tokens,
+                        "`#[trivia]` takes no arguments",
+                    ));
                 }
+                trivia.push(&variant.ident);
+            }
+        }
+    }
+    let is_trivia_fn = if trivia.is_empty() {
+        quote! {}
+    } else {
+        quote! {
+            fn is_trivia(&self) -> bool {
+                matches!(self, #(#ident::#trivia(_))|*)
             }
         }
-        _ => panic!(),
     };
-    TokenStream::from(res)
+    let mut res = vec![];
+    let span_arms = tokens.iter().map(|(variant_ident, _)| {
+        quote! {
+            #ident::#variant_ident(t) => neco_syn::Token::span(t),
+        }
+    });
+    let id_arms = tokens.iter().map(|(variant_ident, _)| {
+        quote! {
+            #ident::#variant_ident(t) => t.id,
+        }
+    });
+    let shift_arms = tokens.iter().map(|(variant_ident, _)| {
+        quote! {
+            #ident::#variant_ident(t) => neco_syn::Token::shift(t, delta),
+        }
+    });
+    res.push(quote! {
+        impl neco_syn::TokenSet for #ident {
+            fn span(&self) -> neco_syn::Span {
+                match self {
+                    #(#span_arms)*
+                }
+            }
+            fn id(&self) -> neco_syn::SyntaxTreeId {
+                match self {
+                    #(#id_arms)*
+                }
+            }
+            fn shift(&mut self, delta: isize) {
+                match self {
+                    #(#shift_arms)*
+                }
+            }
+            #is_trivia_fn
+        }
+    });
+    for (variant_ident, first) in &tokens {
+        res.push(quote! {
+            impl neco_syn::TokenSetMatch<#ident> for #first {
+                fn token_match(set: &#ident) -> Option<#first> {
+                    match set {
+                        #ident::#variant_ident(t) => Some(t.clone()),
+                        _ => None
+                    }
+                }
+            }
+        });
+        res.push(quote! {
+            impl neco_syn::SyntaxTree<#ident> for #first {
+                const NAME: &'static str = <#first as neco_syn::Token>::NAME;
+                fn parse(tokens: &mut neco_syn::Tokens<#ident>) -> neco_syn::ParserResult<#first> {
+                    use neco_syn::TokenSet;
+                    if let Some(t) = tokens.get_token().and_then(|t| t.token_match::<#first>()) {
+                        let res = t.clone();
+                        tokens.next();
+                        neco_syn::ParserResult::Ok(res)
+                    } else {
+                        neco_syn::ParserResult::Fail(tokens.error(<#first as neco_syn::Token>::NAME))
+                    }
+                }
+                fn id(&self) -> neco_syn::SyntaxTreeId {
+                    self.id
+                }
+                fn span(&self) -> neco_syn::Span {
+                    neco_syn::Token::span(self)
+                }
+                fn shift(&mut self, delta: isize) {
+                    neco_syn::Token::shift(self, delta)
+                }
+            }
+        });
+    }
+    Ok(quote! { #(#res)* })
 }
 
-#[proc_macro_derive(TokenSet)]
-pub fn derive_token_set(input: TokenStream) -> TokenStream {
+// #[name = "..."] is used in parse errors instead of the type name
+fn parse_name(attrs: &[syn::Attribute], ident: &syn::Ident) -> syn::Result<TokenStream2> {
+    let mut name = None;
+    for attr in attrs {
+        if !attr.path.is_ident("name") {
+            continue;
+        }
+        if name.is_some() {
+            return Err(syn::Error::new_spanned(
+                attr,
+                "duplicate `#[name]` attribute",
+            ));
+        }
+        match attr.parse_meta()? {
+            syn::Meta::NameValue(syn::MetaNameValue {
+                lit: syn::Lit::Str(lit),
+                ..
+            }) => name = Some(lit),
+            meta => {
+                return Err(syn::Error::new_spanned(
+                    meta,
+                    "expected `#[name = \"...\"]`",
+                ))
+            }
+        }
+    }
+    Ok(match name {
+        Some(lit) => quote! { #lit },
+        None => quote! { stringify!(#ident) },
+    })
+}
+
+#[proc_macro_derive(Token, attributes(text, name))]
+pub fn derive_token(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
-    let data = input.data;
+    into_token_stream(expand_token(input))
+}
+
+fn expand_token(input: DeriveInput) -> syn::Result<TokenStream2> {
     let ident = input.ident;
-    let res = match data {
+    let data_struct = match input.data {
+        Data::Struct(data_struct) => data_struct,
         Data::Enum(data_enum) => {
-            let mut res = vec![];
-            res.push(TokenStream::from(quote! {
-                impl neco_syn::TokenSet for #ident {}
-            }));
-            for variant in data_enum.variants {
-                let variant_ident = variant.ident.clone();
-                if let Fields::Unnamed(fields_unnamed) = variant.fields {
-                    let first = fields_unnamed.unnamed.iter().next().unwrap().clone();
-                    res.push(TokenStream::from(quote! {
-                        impl neco_syn::TokenSetMatch<#ident> for #first {
-                            fn token_match(set: &#ident) -> Option<#first> {
-                                match set {
-                                    #ident::#variant_ident(t) => Some(t.clone()),
-                                    _ => None
-                                }
-                            }
-                        }
-                    }));
-                    res.push(TokenStream::from(quote!{
-                        impl neco_syn::SyntaxTree<#ident> for #first {
-                            fn parse(tokens: &mut neco_syn::Tokens<#ident>) -> neco_syn::ParserResult<#first> {
-                                use neco_syn::TokenSet;
-                                let initial_i = tokens.get_i();
-                                let t = tokens.get_token();
-                                if let Some(t) = t.token_match::<#first>() {
-                                    let res = t.clone();
-                                    tokens.next();
-                                    neco_syn::ParserResult::Ok(res)
-                                } else {
-                                    neco_syn::ParserResult::Fail
-                                }
-                            }
-                            fn id(&self) -> neco_syn::SyntaxTreeId {
-                                self.id
-                            }
-                        }
-                    }));
+            return Err(syn::Error::new(
+                data_enum.enum_token.span,
+                "`Token` can only be derived for structs",
+            ))
+        }
+        Data::Union(data_union) => {
+            return Err(syn::Error::new(
+                data_union.union_token.span,
+                "`Token` can only be derived for structs",
+            ))
+        }
+    };
+    // the TokenSet derive reads `id`, and `span` is returned here
+    for name in &["id", "span"] {
+        let found = data_struct
+            .fields
+            .iter()
+            .any(|field| field.ident.as_ref().is_some_and(|ident| ident == name));
+        if !found {
+            let message = match *name {
+                "id" => "expected a field `id: SyntaxTreeId`",
+                _ => "expected a field `span: Span`",
+            };
+            return Err(syn::Error::new(data_struct.struct_token.span, message));
+        }
+    }
+    let mut text = None;
+    for field in &data_struct.fields {
+        for attr in &field.attrs {
+            if !attr.path.is_ident("text") {
+                continue;
+            }
+            if text.is_some() {
+                return Err(syn::Error::new_spanned(
+                    attr,
+                    "duplicate `#[text]` attribute",
+                ));
+            }
+            match &field.ident {
+                Some(field_ident) => text = Some(field_ident),
+                None => unreachable!(),
+            }
+        }
+    }
+    // #[text("...")] on the struct is the text of every token of the type
+    let mut fixed_text = None;
+    for attr in &input.attrs {
+        if attr.path.is_ident("text") {
+            if fixed_text.is_some() || text.is_some() {
+                return Err(syn::Error::new_spanned(
+                    attr,
+                    "duplicate `#[text]` attribute",
+                ));
+            }
+            fixed_text = Some(attr.parse_args::<syn::LitStr>()?);
+        }
+    }
+    let name = parse_name(&input.attrs, &ident)?;
+    let text_fn = match (text, &fixed_text) {
+        (Some(field_ident), _) => quote! {
+            fn text(&self) -> Option<String> {
+                Some(self.#field_ident.to_string())
+            }
+        },
+        (None, Some(lit)) => quote! {
+            fn text(&self) -> Option<String> {
+                Some(#lit.to_string())
+            }
+        },
+        (None, None) => quote! {},
+    };
+    // tokens with other fields are not built by the lexer
+    let lexeme_only = data_struct.fields.iter().all(|field| {
+        field
+            .ident
+            .as_ref()
+            .is_some_and(|field_ident| field_ident == "id" || field_ident == "span")
+            || field.ident.as_ref() == text
+    });
+    let from_lexeme = match text {
+        Some(field_ident) if lexeme_only => quote! {
+            impl neco_syn::lexer::FromLexeme for #ident {
+                fn from_lexeme(id: neco_syn::SyntaxTreeId, span: neco_syn::Span, text: &str) -> Self {
+                    #ident {
+                        id,
+                        span,
+                        #field_ident: neco_syn::lexer::FromText::from_text(text),
+                    }
+                }
+            }
+        },
+        None if lexeme_only => quote! {
+            impl neco_syn::lexer::FromLexeme for #ident {
+                fn from_lexeme(id: neco_syn::SyntaxTreeId, span: neco_syn::Span, _text: &str) -> Self {
+                    #ident { id, span }
                 }
             }
-            res
+        },
+        _ => quote! {},
+    };
+    let walk = expand_leaf_walk(&ident);
+    Ok(quote! {
+        impl neco_syn::Token for #ident {
+            const NAME: &'static str = #name;
+            fn span(&self) -> neco_syn::Span {
+                self.span
+            }
+            fn shift(&mut self, delta: isize) {
+                self.span = self.span.shift(delta);
+            }
+            #text_fn
+        }
+        #from_lexeme
+        #walk
+    })
+}
+
+#[proc_macro_derive(TokenValue, attributes(value))]
+pub fn derive_token_value(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    into_token_stream(expand_token_value(input))
+}
+
+fn expand_token_value(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let mut value = None;
+    for attr in &input.attrs {
+        if attr.path.is_ident("value") {
+            if value.is_some() {
+                return Err(syn::Error::new_spanned(
+                    attr,
+                    "duplicate `#[value(...)]` attribute",
+                ));
+            }
+            value = Some(attr.parse_args::<syn::LitStr>()?);
+        }
+    }
+    let ident = input.ident;
+    let value = match value {
+        Some(value) => value,
+        None => {
+            return Err(syn::Error::new(
+                ident.span(),
+                "missing `#[value(\"...\")]` attribute",
+            ))
+        }
+    };
+    let data_struct = match input.data {
+        Data::Struct(data_struct) => data_struct,
+        Data::Enum(data_enum) => {
+            return Err(syn::Error::new(
+                data_enum.enum_token.span,
+                "`TokenValue` can only be derived for structs",
+            ))
+        }
+        Data::Union(data_union) => {
+            return Err(syn::Error::new(
+                data_union.union_token.span,
+                "`TokenValue` can only be derived for structs",
+            ))
         }
-        _ => panic!(),
     };
-    let mut res2 = TokenStream::new();
-    res2.extend(res);
-    res2
+    if data_struct.fields.len() != 1 {
+        return Err(syn::Error::new(
+            data_struct.struct_token.span,
+            "expected a single field holding the base token",
+        ));
+    }
+    let field = data_struct.fields.iter().next().unwrap();
+    let base = &field.ty;
+    let (construct, access) = match &field.ident {
+        Some(field_ident) => (
+            quote! { #ident { #field_ident: base } },
+            quote! { self.#field_ident },
+        ),
+        None => (quote! { #ident(base) }, quote! { self.0 }),
+    };
+    let walk = expand_leaf_walk(&ident);
+    Ok(quote! {
+        impl neco_syn::TokenValue for #ident {
+            type Base = #base;
+            const VALUE: &'static str = #value;
+            fn from_base(base: #base) -> Self {
+                #construct
+            }
+            fn base(&self) -> &#base {
+                &#access
+            }
+        }
+        impl<S: neco_syn::TokenSet> neco_syn::SyntaxTree<S> for #ident
+        where
+            #base: neco_syn::SyntaxTree<S>,
+        {
+            const NAME: &'static str = #value;
+            fn parse(tokens: &mut neco_syn::Tokens<S>) -> neco_syn::ParserResult<#ident> {
+                neco_syn::parse_token_value(tokens)
+            }
+            fn id(&self) -> neco_syn::SyntaxTreeId {
+                neco_syn::SyntaxTree::<S>::id(&#access)
+            }
+            fn span(&self) -> neco_syn::Span {
+                neco_syn::SyntaxTree::<S>::span(&#access)
+            }
+            fn shift(&mut self, delta: isize) {
+                neco_syn::SyntaxTree::<S>::shift(&mut #access, delta)
+            }
+        }
+        #walk
+    })
 }
 
-#[proc_macro_derive(Token)]
-pub fn derive_token(input: TokenStream) -> TokenStream {
+#[proc_macro_derive(Lexer, attributes(literal, pattern))]
+pub fn derive_lexer(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
-    let data = input.data;
+    into_token_stream(expand_lexer(input))
+}
+
+fn expand_lexer(input: DeriveInput) -> syn::Result<TokenStream2> {
     let ident = input.ident;
-    let res = match data {
+    let data_enum = match input.data {
+        Data::Enum(data_enum) => data_enum,
         Data::Struct(data_struct) => {
+            return Err(syn::Error::new(
+                data_struct.struct_token.span,
+                "`Lexer` can only be derived for enums",
+            ))
+        }
+        Data::Union(data_union) => {
+            return Err(syn::Error::new(
+                data_union.union_token.span,
+                "`Lexer` can only be derived for enums",
+            ))
+        }
+    };
+    let mut patterns = vec![];
+    let mut arms = vec![];
+    for variant in &data_enum.variants {
+        let variant_ident = &variant.ident;
+        for attr in &variant.attrs {
+            let literal = attr.path.is_ident("literal");
+            if !literal && !attr.path.is_ident("pattern") {
+                continue;
+            }
+            match &variant.fields {
+                Fields::Unnamed(fields_unnamed) if fields_unnamed.unnamed.len() == 1 => {}
+                _ => {
+                    return Err(syn::Error::new_spanned(
+                        variant,
+                        "expected a variant with a single token, like `Ident(TokenIdent)`",
+                    ))
+                }
+            }
+            let lit = attr.parse_args::<syn::LitStr>()?;
+            let value = lit.value();
+            if value.is_empty() {
+                return Err(syn::Error::new_spanned(&lit, "empty pattern"));
+            }
+            let pattern = if literal {
+                quote! { neco_syn::lexer::Pattern::Literal(#value.to_string()) }
+            } else {
+                match pattern::parse(&value) {
+                    Ok(pattern) => pattern,
+                    Err(message) => {
+                        return Err(syn::Error::new_spanned(
+                            &lit,
+                            format!("invalid pattern: {}", message),
+                        ))
+                    }
+                }
+            };
+            let index = patterns.len();
+            patterns.push(pattern);
+            arms.push(quote! {
+                #index => #ident::#variant_ident(neco_syn::lexer::FromLexeme::from_lexeme(id, span, text)),
+            });
+        }
+    }
+    Ok(quote! {
+        impl neco_syn::lexer::Lexer for #ident {
+            fn patterns() -> Vec<neco_syn::lexer::Pattern> {
+                vec![#(#patterns),*]
+            }
+            fn token(index: usize, id: neco_syn::SyntaxTreeId, span: neco_syn::Span, text: &str) -> Self {
+                match index {
+                    #(#arms)*
+                    _ => unreachable!(),
+                }
+            }
+        }
+    })
+}
+
+struct VisitorInput {
+    vis: syn::Visibility,
+    visit: syn::Ident,
+    visit_mut: Option<syn::Ident>,
+    nodes: syn::punctuated::Punctuated<syn::Path, syn::Token![,]>,
+}
+
+impl syn::parse::Parse for VisitorInput {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let vis = input.parse()?;
+        input.parse::<syn::Token![trait]>()?;
+        let visit = input.parse()?;
+        let visit_mut = if input.peek(syn::Token![,]) {
+            input.parse::<syn::Token![,]>()?;
+            Some(input.parse()?)
+        } else {
+            None
+        };
+        let content;
+        syn::braced!(content in input);
+        let nodes = content.parse_terminated(syn::Path::parse)?;
+        Ok(VisitorInput {
+            vis,
+            visit,
+            visit_mut,
+            nodes,
+        })
+    }
+}
+
+// TokenLit -> token_lit
+fn snake_case(ident: &syn::Ident) -> String {
+    let mut res = String::new();
+    for (i, c) in ident.to_string().chars().enumerate() {
+        if c.is_uppercase() {
+            if i > 0 {
+                res.push('_');
+            }
+            res.extend(c.to_lowercase());
+        } else {
+            res.push(c);
+        }
+    }
+    res
+}
+
+// visitor! {
+//     pub trait Visit, VisitMut {
+//         Stmt, Expr, TokenLit,
+//     }
+// }
+// declares the visitor traits with a method per node, which walks the node by default.
+#[proc_macro]
+pub fn visitor(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as VisitorInput);
+    let vis = &input.vis;
+    let visit = &input.visit;
+    let mut methods = vec![];
+    let mut methods_mut = vec![];
+    let mut impls = vec![];
+    for node in &input.nodes {
+        let name = snake_case(&node.segments.last().unwrap().ident);
+        let method = format_ident!("visit_{}", name);
+        let method_mut = format_ident!("visit_{}_mut", name);
+        methods.push(quote! {
+            fn #method(&mut self, node: &#node) {
+                neco_syn::visit::Walk::walk(node, self);
+            }
+        });
+        impls.push(quote! {
+            impl<V: #visit + ?Sized> neco_syn::visit::Accept<V> for #node {
+                fn accept(&self, v: &mut V) {
+                    v.#method(self);
+                }
+            }
+        });
+        if let Some(visit_mut) = &input.visit_mut {
+            methods_mut.push(quote! {
+                fn #method_mut(&mut self, node: &mut #node) {
+                    neco_syn::visit::WalkMut::walk_mut(node, self);
+                }
+            });
+            impls.push(quote! {
+                impl<V: #visit_mut + ?Sized> neco_syn::visit::AcceptMut<V> for #node {
+                    fn accept_mut(&mut self, v: &mut V) {
+                        v.#method_mut(self);
+                    }
+                }
+            });
+        }
+    }
+    let visit_mut = input.visit_mut.as_ref().map(|visit_mut| {
+        quote! {
+            #vis trait #visit_mut {
+                #(#methods_mut)*
+            }
+        }
+    });
+    TokenStream::from(quote! {
+        #vis trait #visit {
+            #(#methods)*
+        }
+        #visit_mut
+        #(#impls)*
+    })
+}
+
+fn has_text_attribute(attrs: &[syn::Attribute]) -> bool {
+    attrs.iter().any(|attr| attr.path.is_ident("text"))
+}
+
+#[proc_macro_derive(ToSource, attributes(text))]
+pub fn derive_to_source(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    into_token_stream(expand_to_source(input))
+}
+
+fn expand_to_source(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let ident = &input.ident;
+    let body = match &input.data {
+        // a token, which is printed by its text
+        Data::Struct(data_struct)
+            if has_text_attribute(&input.attrs)
+                || data_struct
+                    .fields
+                    .iter()
+                    .any(|field| has_text_attribute(&field.attrs)) =>
+        {
             quote! {
-                impl neco_syn::Token for #ident {
-                    fn span(&self) -> neco_syn::Span {
-                        self.span
+                if let Some(text) = neco_syn::Token::text(self) {
+                    printer.token(&text);
+                }
+            }
+        }
+        Data::Struct(data_struct) => {
+            let mut stmts = vec![];
+            for (i, field) in consumed_fields(&data_struct.fields)? {
+                let member = match &field.ident {
+                    Some(field_ident) => quote! { #field_ident },
+                    None => {
+                        let i = syn::Index::from(i);
+                        quote! { #i }
                     }
+                };
+                stmts.push(quote! {
+                    neco_syn::print::ToSource::to_source(&self.#member, printer);
+                });
+            }
+            quote! { #(#stmts)* }
+        }
+        Data::Enum(data_enum) => {
+            let mut arms = vec![];
+            for variant in &data_enum.variants {
+                let fields = consumed_fields(&variant.fields)?;
+                let (pattern, vars) = variant_pattern(ident, variant, &fields);
+                arms.push(quote! {
+                    #pattern => {
+                        #(neco_syn::print::ToSource::to_source(#vars, printer);)*
+                    }
+                });
+            }
+            quote! {
+                match self {
+                    #(#arms)*
                 }
             }
         }
-        _ => panic!(),
+        Data::Union(data_union) => {
+            return Err(syn::Error::new(
+                data_union.union_token.span,
+                "unions are not supported",
+            ))
+        }
     };
-    TokenStream::from(res)
+    Ok(quote! {
+        impl neco_syn::print::ToSource for #ident {
+            #[allow(unused_variables)]
+            fn to_source(&self, printer: &mut neco_syn::print::Printer) {
+                #body
+            }
+        }
+    })
 }
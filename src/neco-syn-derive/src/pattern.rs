@@ -0,0 +1,175 @@
+// parses the patterns of #[derive(Lexer)] into neco_syn::lexer::Pattern expressions.
+//
+// alt    := seq ('|' seq)*
+// seq    := repeat*
+// repeat := atom ('*' | '+' | '?')*
+// atom   := '(' alt ')' | '[' '^'? class* ']' | '.' | '\' escape | char
+
+use proc_macro2::TokenStream as TokenStream2;
+use quote::*;
+
+struct Parser {
+    cs: Vec<char>,
+    i: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.cs.get(self.i).copied()
+    }
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek();
+        self.i += 1;
+        c
+    }
+    fn alt(&mut self) -> Result<TokenStream2, String> {
+        let mut alternatives = vec![self.seq()?];
+        while self.peek() == Some('|') {
+            self.i += 1;
+            alternatives.push(self.seq()?);
+        }
+        if alternatives.len() == 1 {
+            return Ok(alternatives.pop().unwrap());
+        }
+        Ok(quote! { neco_syn::lexer::Pattern::Alt(vec![#(#alternatives),*]) })
+    }
+    fn seq(&mut self) -> Result<TokenStream2, String> {
+        let mut patterns = vec![];
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            patterns.push(self.repeat()?);
+        }
+        if patterns.len() == 1 {
+            return Ok(patterns.pop().unwrap());
+        }
+        Ok(quote! { neco_syn::lexer::Pattern::Seq(vec![#(#patterns),*]) })
+    }
+    fn repeat(&mut self) -> Result<TokenStream2, String> {
+        let mut pattern = self.atom()?;
+        loop {
+            let (min, max) = match self.peek() {
+                Some('*') => (0usize, quote! { None }),
+                Some('+') => (1, quote! { None }),
+                Some('?') => (0, quote! { Some(1) }),
+                _ => break,
+            };
+            self.i += 1;
+            pattern = quote! {
+                neco_syn::lexer::Pattern::Repeat {
+                    pattern: Box::new(#pattern),
+                    min: #min,
+                    max: #max,
+                }
+            };
+        }
+        Ok(pattern)
+    }
+    fn atom(&mut self) -> Result<TokenStream2, String> {
+        match self.next() {
+            Some('(') => {
+                let res = self.alt()?;
+                if self.next() != Some(')') {
+                    return Err("unclosed `(`".to_string());
+                }
+                Ok(res)
+            }
+            Some('[') => self.class(),
+            Some('.') => Ok(quote! { neco_syn::lexer::Pattern::Any }),
+            Some('\\') => match self.escape()? {
+                Escape::Char(c) => Ok(quote! { neco_syn::lexer::Pattern::Char(#c) }),
+                Escape::Class(item) => Ok(quote! {
+                    neco_syn::lexer::Pattern::Class {
+                        negated: false,
+                        items: vec![#item],
+                    }
+                }),
+            },
+            Some(c @ ('*' | '+' | '?')) => Err(format!("`{}` must follow a pattern", c)),
+            Some(')') => Err("unmatched `)`".to_string()),
+            Some(c) => Ok(quote! { neco_syn::lexer::Pattern::Char(#c) }),
+            None => Err("unexpected end of pattern".to_string()),
+        }
+    }
+    fn class(&mut self) -> Result<TokenStream2, String> {
+        let negated = self.peek() == Some('^');
+        if negated {
+            self.i += 1;
+        }
+        let mut items = vec![];
+        loop {
+            let begin = match self.next() {
+                Some(']') => break,
+                Some('\\') => match self.escape()? {
+                    Escape::Char(c) => c,
+                    Escape::Class(item) => {
+                        items.push(item);
+                        continue;
+                    }
+                },
+                Some(c) => c,
+                None => return Err("unclosed `[`".to_string()),
+            };
+            let end = if self.peek() == Some('-') && self.cs.get(self.i + 1) != Some(&']') {
+                self.i += 1;
+                match self.next() {
+                    Some('\\') => match self.escape()? {
+                        Escape::Char(c) => c,
+                        Escape::Class(_) => {
+                            return Err("a range cannot end with a class".to_string())
+                        }
+                    },
+                    Some(c) => c,
+                    None => return Err("unclosed `[`".to_string()),
+                }
+            } else {
+                begin
+            };
+            if begin > end {
+                return Err(format!("invalid range `{}-{}`", begin, end));
+            }
+            items.push(quote! { neco_syn::lexer::ClassItem::Range(#begin, #end) });
+        }
+        Ok(quote! {
+            neco_syn::lexer::Pattern::Class {
+                negated: #negated,
+                items: vec![#(#items),*],
+            }
+        })
+    }
+    fn escape(&mut self) -> Result<Escape, String> {
+        let c = match self.next() {
+            Some(c) => c,
+            None => return Err("unexpected end of pattern after `\\`".to_string()),
+        };
+        Ok(match c {
+            's' => Escape::Class(quote! { neco_syn::lexer::ClassItem::Whitespace }),
+            'd' => Escape::Class(quote! { neco_syn::lexer::ClassItem::Digit }),
+            'w' => Escape::Class(quote! { neco_syn::lexer::ClassItem::Word }),
+            'n' => Escape::Char('\n'),
+            'r' => Escape::Char('\r'),
+            't' => Escape::Char('\t'),
+            c if c.is_ascii_alphanumeric() => return Err(format!("unknown escape `\\{}`", c)),
+            c => Escape::Char(c),
+        })
+    }
+}
+
+enum Escape {
+    Char(char),
+    Class(TokenStream2),
+}
+
+pub fn parse(pattern: &str) -> Result<TokenStream2, String> {
+    let mut parser = Parser {
+        cs: pattern.chars().collect(),
+        i: 0,
+    };
+    let res = parser.alt()?;
+    match parser.peek() {
+        Some(')') => Err("unmatched `)`".to_string()),
+        Some(c) => Err(format!("unexpected `{}`", c)),
+        None => Ok(res),
+    }
+}
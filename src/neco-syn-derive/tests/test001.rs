@@ -5,20 +5,25 @@ use neco_syn_derive::*;
 pub struct TokenEq {
     id: SyntaxTreeId,
     span: Span,
-    c: char,
 }
 
 #[derive(Debug, Clone, Token)]
 pub struct TokenLit {
     id: SyntaxTreeId,
     span: Span,
-    c: char,
+}
+
+#[derive(Debug, Clone, Token)]
+pub struct TokenSemi {
+    id: SyntaxTreeId,
+    span: Span,
 }
 
 #[derive(Clone, TokenSet)]
 pub enum TestToken {
     Eq(TokenEq),
     Lit(TokenLit),
+    Semi(TokenSemi),
 }
 
 #[derive(Debug, SyntaxTree)]
@@ -47,85 +52,177 @@ pub enum DefOrDef2 {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use neco_syn::{gen_next_syntax_tree_id, Tokens};
+    use neco_syn::{ParserResult, SyntaxTree, SyntaxTreeIdManager, Tokens};
+    use neco_table::SubTable;
 
     #[test]
     fn test001_success_1() {
-        let mut tokens: Tokens<TestToken> = Tokens::new(vec![
-            TestToken::Lit(TokenLit {
-                id: gen_next_syntax_tree_id(),
-                span: Span::new(),
-                c: 'x',
-            }),
-            TestToken::Eq(TokenEq {
-                id: gen_next_syntax_tree_id(),
-                span: Span::new(),
-                c: '=',
-            }),
-            TestToken::Lit(TokenLit {
-                id: gen_next_syntax_tree_id(),
-                span: Span::new(),
-                c: '1',
-            }),
-        ]);
+        let mut ids = SyntaxTreeIdManager::new();
+        let mut tokens: Tokens<TestToken> = Tokens::new(
+            vec![
+                TestToken::Lit(TokenLit {
+                    id: ids.create(),
+                    span: Span::new(),
+                }),
+                TestToken::Eq(TokenEq {
+                    id: ids.create(),
+                    span: Span::new(),
+                }),
+                TestToken::Lit(TokenLit {
+                    id: ids.create(),
+                    span: Span::new(),
+                }),
+            ],
+            ids,
+        );
         let t = tokens.parse::<Def>();
         assert!(t.is_ok());
     }
 
     #[test]
     fn test001_success_2() {
-        let mut tokens: Tokens<TestToken> = Tokens::new(vec![
-            TestToken::Lit(TokenLit {
-                id: gen_next_syntax_tree_id(),
-                span: Span::new(),
-                c: 'x',
-            }),
-            TestToken::Eq(TokenEq {
-                id: gen_next_syntax_tree_id(),
-                span: Span::new(),
-                c: '=',
-            }),
-            TestToken::Lit(TokenLit {
-                id: gen_next_syntax_tree_id(),
-                span: Span::new(),
-                c: '1',
-            }),
-        ]);
+        let mut ids = SyntaxTreeIdManager::new();
+        let mut tokens: Tokens<TestToken> = Tokens::new(
+            vec![
+                TestToken::Lit(TokenLit {
+                    id: ids.create(),
+                    span: Span::new(),
+                }),
+                TestToken::Eq(TokenEq {
+                    id: ids.create(),
+                    span: Span::new(),
+                }),
+                TestToken::Lit(TokenLit {
+                    id: ids.create(),
+                    span: Span::new(),
+                }),
+            ],
+            ids,
+        );
         let t = tokens.parse::<DefOrDef2>();
         assert!(t.is_ok());
     }
 
     #[test]
     fn test001_success_3() {
-        let mut tokens: Tokens<TestToken> = Tokens::new(vec![TestToken::Eq(TokenEq {
-            id: gen_next_syntax_tree_id(),
-            span: Span::new(),
-            c: '=',
-        })]);
+        let mut ids = SyntaxTreeIdManager::new();
+        let mut tokens: Tokens<TestToken> = Tokens::new(
+            vec![TestToken::Eq(TokenEq {
+                id: ids.create(),
+                span: Span::new(),
+            })],
+            ids,
+        );
         let t = tokens.parse::<DefOrDef2>();
         assert!(t.is_ok());
     }
 
     #[test]
     fn test001_fail() {
-        let mut tokens: Tokens<TestToken> = Tokens::new(vec![
-            TestToken::Lit(TokenLit {
-                id: gen_next_syntax_tree_id(),
-                span: Span::new(),
-                c: 'x',
-            }),
-            TestToken::Eq(TokenEq {
-                id: gen_next_syntax_tree_id(),
-                span: Span::new(),
-                c: '=',
-            }),
-            TestToken::Eq(TokenEq {
-                id: gen_next_syntax_tree_id(),
-                span: Span::new(),
-                c: '=',
-            }),
-        ]);
+        let mut ids = SyntaxTreeIdManager::new();
+        let mut tokens: Tokens<TestToken> = Tokens::new(
+            vec![
+                TestToken::Lit(TokenLit {
+                    id: ids.create(),
+                    span: Span::new(),
+                }),
+                TestToken::Eq(TokenEq {
+                    id: ids.create(),
+                    span: Span::new(),
+                }),
+                TestToken::Eq(TokenEq {
+                    id: ids.create(),
+                    span: Span::new(),
+                }),
+            ],
+            ids,
+        );
         let t = tokens.parse::<Def>();
         assert!(!t.is_ok())
     }
+
+    #[test]
+    fn test001_fail_error() {
+        let mut ids = SyntaxTreeIdManager::new();
+        let mut tokens: Tokens<TestToken> = Tokens::new(
+            vec![
+                TestToken::Lit(TokenLit {
+                    id: ids.create(),
+                    span: Span::new(),
+                }),
+                TestToken::Eq(TokenEq {
+                    id: ids.create(),
+                    span: Span::new(),
+                }),
+                TestToken::Eq(TokenEq {
+                    id: ids.create(),
+                    span: Span::new(),
+                }),
+            ],
+            ids,
+        );
+        let t = tokens.parse::<DefOrDef2>();
+        let e = t.error().unwrap();
+        assert_eq!(e.i(), 2);
+        assert_eq!(
+            e.expected().iter().copied().collect::<Vec<_>>(),
+            vec!["TokenLit"]
+        );
+        assert_eq!(tokens.get_i(), 0);
+    }
+
+    #[test]
+    fn test001_fail_error_merged() {
+        let mut ids = SyntaxTreeIdManager::new();
+        let mut tokens: Tokens<TestToken> = Tokens::new(
+            vec![TestToken::Semi(TokenSemi {
+                id: ids.create(),
+                span: Span::new(),
+            })],
+            ids,
+        );
+        let t = tokens.parse::<DefOrDef2>();
+        assert!(t.is_fail());
+        let e = t.error().unwrap();
+        assert_eq!(e.i(), 0);
+        assert_eq!(
+            e.expected().iter().copied().collect::<Vec<_>>(),
+            vec!["TokenEq", "TokenLit"]
+        );
+    }
+
+    #[test]
+    fn test001_unique_ids() {
+        let mut ids = SyntaxTreeIdManager::new();
+        let mut tokens: Tokens<TestToken> = Tokens::new(
+            vec![
+                TestToken::Lit(TokenLit {
+                    id: ids.create(),
+                    span: Span::new(),
+                }),
+                TestToken::Eq(TokenEq {
+                    id: ids.create(),
+                    span: Span::new(),
+                }),
+                TestToken::Lit(TokenLit {
+                    id: ids.create(),
+                    span: Span::new(),
+                }),
+            ],
+            ids,
+        );
+        let def = match tokens.parse::<Def>() {
+            ParserResult::Ok(def) => def,
+            _ => panic!(),
+        };
+        let mut table = SubTable::new();
+        table.insert(def.id(), "def");
+        table.insert(def.ident.id(), "ident");
+        table.insert(def.eq.id(), "eq");
+        table.insert(def.expr.id(), "expr");
+        assert_eq!(table.get(def.id()), Some(&"def"));
+        assert_eq!(table.get(def.ident.id()), Some(&"ident"));
+        assert_eq!(table.get(def.eq.id()), Some(&"eq"));
+        assert_eq!(table.get(def.expr.id()), Some(&"expr"));
+    }
 }
@@ -0,0 +1,110 @@
+use neco_syn::{Rep0, Span, SyntaxTreeId};
+use neco_syn_derive::*;
+
+#[derive(Debug, Clone, Token)]
+pub struct TokenEq {
+    id: SyntaxTreeId,
+    span: Span,
+}
+
+#[derive(Debug, Clone, Token)]
+pub struct TokenLit {
+    id: SyntaxTreeId,
+    span: Span,
+}
+
+#[derive(Debug, Clone, Token)]
+pub struct TokenSemi {
+    id: SyntaxTreeId,
+    span: Span,
+}
+
+#[derive(Clone, TokenSet)]
+pub enum TestToken {
+    Eq(TokenEq),
+    Lit(TokenLit),
+    Semi(TokenSemi),
+}
+
+#[derive(SyntaxTree)]
+#[TokenSet(TestToken)]
+#[recover(TokenSemi)]
+pub struct Stmt {
+    id: SyntaxTreeId,
+    ident: TokenLit,
+    eq: TokenEq,
+    expr: TokenLit,
+    semi: TokenSemi,
+}
+
+#[derive(SyntaxTree)]
+#[TokenSet(TestToken)]
+pub struct Block {
+    id: SyntaxTreeId,
+    stmts: Rep0<Stmt>,
+    end: TokenEq,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use neco_syn::{ParserResult, SyntaxTreeIdManager, Tokens};
+
+    fn tokens(s: &str) -> Tokens<TestToken> {
+        let mut ids = SyntaxTreeIdManager::new();
+        let mut res = vec![];
+        for c in s.chars() {
+            let id = ids.create();
+            let span = Span::new();
+            res.push(match c {
+                'l' => TestToken::Lit(TokenLit { id, span }),
+                '=' => TestToken::Eq(TokenEq { id, span }),
+                ';' => TestToken::Semi(TokenSemi { id, span }),
+                _ => unreachable!(),
+            });
+        }
+        Tokens::new(res, ids)
+    }
+
+    #[test]
+    fn test002_without_recovery() {
+        // x = 1 ; x = = ; y = 2 ; =
+        let mut tokens = tokens("l=l;l==;l=l;=");
+        let t = tokens.parse::<Block>();
+        assert!(t.is_fail());
+        assert_eq!(t.error().unwrap().i(), 4);
+        assert!(tokens.errors().is_empty());
+    }
+
+    #[test]
+    fn test002_recovery() {
+        let mut tokens = tokens("l=l;l==;l=l;=");
+        tokens.set_recovery(true);
+        let t = tokens.parse::<Block>();
+        let block = match t {
+            ParserResult::Ok(block) => block,
+            _ => panic!(),
+        };
+        assert_eq!(block.stmts.ts.len(), 2);
+        assert_eq!(block.stmts.errors.len(), 1);
+        assert_eq!(block.stmts.errors[0].index, 1);
+        assert_eq!(block.stmts.errors[0].error.i(), 6);
+        assert_eq!(tokens.errors().len(), 1);
+        assert_eq!(tokens.get_i(), 13);
+    }
+
+    #[test]
+    fn test002_recovery_at_first_token() {
+        // x = 1 ; ; 2 ; y = 2 ; =
+        let mut tokens = tokens("l=l;;l;l=l;=");
+        tokens.set_recovery(true);
+        let block = match tokens.parse::<Block>() {
+            ParserResult::Ok(block) => block,
+            _ => panic!(),
+        };
+        assert_eq!(block.stmts.ts.len(), 2);
+        let errors: Vec<_> = block.stmts.errors.iter().map(|e| e.error.i()).collect();
+        assert_eq!(errors, vec![4, 6]);
+        assert_eq!(tokens.get_i(), 12);
+    }
+}
@@ -0,0 +1,123 @@
+use neco_syn::{ParserResult, Span, SyntaxTree, SyntaxTreeId, Tokens};
+use neco_syn_derive::*;
+use std::cell::Cell;
+
+#[derive(Debug, Clone, Token)]
+pub struct TokenEq {
+    id: SyntaxTreeId,
+    span: Span,
+}
+
+#[derive(Debug, Clone, Token)]
+pub struct TokenLit {
+    id: SyntaxTreeId,
+    span: Span,
+}
+
+#[derive(Debug, Clone, Token)]
+pub struct TokenSemi {
+    id: SyntaxTreeId,
+    span: Span,
+}
+
+#[derive(Clone, TokenSet)]
+pub enum TestToken {
+    Eq(TokenEq),
+    Lit(TokenLit),
+    Semi(TokenSemi),
+}
+
+thread_local! {
+    static COUNT: Cell<usize> = const { Cell::new(0) };
+}
+
+#[derive(Debug, Clone)]
+pub struct CountedLit {
+    lit: TokenLit,
+}
+
+impl SyntaxTree<TestToken> for CountedLit {
+    const NAME: &'static str = "counted literal";
+    fn parse(tokens: &mut Tokens<TestToken>) -> ParserResult<CountedLit> {
+        COUNT.with(|count| count.set(count.get() + 1));
+        match tokens.parse::<TokenLit>() {
+            ParserResult::Ok(lit) => ParserResult::Ok(CountedLit { lit }),
+            ParserResult::Fail(e) => ParserResult::Fail(e),
+            ParserResult::Err(e) => ParserResult::Err(e),
+        }
+    }
+    fn id(&self) -> SyntaxTreeId {
+        self.lit.id
+    }
+    fn span(&self) -> Span {
+        self.lit.span
+    }
+    fn shift(&mut self, delta: isize) {
+        self.lit.span = self.lit.span.shift(delta);
+    }
+}
+
+#[derive(Debug, Clone, SyntaxTree)]
+#[TokenSet(TestToken)]
+#[memo]
+pub struct Pair {
+    id: SyntaxTreeId,
+    a: CountedLit,
+    b: TokenLit,
+}
+
+#[derive(Debug, SyntaxTree)]
+#[TokenSet(TestToken)]
+pub struct PairSemi {
+    id: SyntaxTreeId,
+    pair: Pair,
+    semi: TokenSemi,
+}
+
+#[derive(Debug, SyntaxTree)]
+#[TokenSet(TestToken)]
+pub struct PairEq {
+    id: SyntaxTreeId,
+    pair: Pair,
+    eq: TokenEq,
+}
+
+#[derive(Debug, SyntaxTree)]
+#[TokenSet(TestToken)]
+pub enum Stmt {
+    Semi(PairSemi),
+    Eq(PairEq),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use neco_syn::SyntaxTreeIdManager;
+
+    #[test]
+    fn test003_memo() {
+        let mut ids = SyntaxTreeIdManager::new();
+        let mut tokens: Tokens<TestToken> = Tokens::new(
+            vec![
+                TestToken::Lit(TokenLit {
+                    id: ids.create(),
+                    span: Span::new(),
+                }),
+                TestToken::Lit(TokenLit {
+                    id: ids.create(),
+                    span: Span::new(),
+                }),
+                TestToken::Eq(TokenEq {
+                    id: ids.create(),
+                    span: Span::new(),
+                }),
+            ],
+            ids,
+        );
+        COUNT.with(|count| count.set(0));
+        let t = tokens.parse::<Stmt>();
+        assert!(matches!(t, ParserResult::Ok(Stmt::Eq(_))));
+        assert_eq!(tokens.get_i(), 3);
+        assert_eq!(COUNT.with(|count| count.get()), 1);
+    }
+}
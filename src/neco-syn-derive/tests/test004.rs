@@ -0,0 +1,162 @@
+use neco_syn::{Span, SyntaxTreeId};
+use neco_syn_derive::*;
+
+#[derive(Debug, Clone, Token)]
+pub struct TokenLit {
+    id: SyntaxTreeId,
+    span: Span,
+    c: char,
+}
+
+#[derive(Debug, Clone, Token)]
+pub struct TokenPlus {
+    id: SyntaxTreeId,
+    span: Span,
+}
+
+#[derive(Debug, Clone, Token)]
+pub struct TokenStar {
+    id: SyntaxTreeId,
+    span: Span,
+}
+
+#[derive(Debug, Clone, Token)]
+pub struct TokenSemi {
+    id: SyntaxTreeId,
+    span: Span,
+}
+
+#[derive(Clone, TokenSet)]
+pub enum TestToken {
+    Lit(TokenLit),
+    Plus(TokenPlus),
+    Star(TokenStar),
+    Semi(TokenSemi),
+}
+
+// Expr := Expr + Term | Term
+#[derive(Debug, Clone, SyntaxTree)]
+#[TokenSet(TestToken)]
+#[memo]
+pub enum Expr {
+    Add(Add),
+    Term(Term),
+}
+
+#[derive(Debug, Clone, SyntaxTree)]
+#[TokenSet(TestToken)]
+pub struct Add {
+    id: SyntaxTreeId,
+    lhs: Box<Expr>,
+    plus: TokenPlus,
+    rhs: Term,
+}
+
+// Term := Term * Lit | Lit
+#[derive(Debug, Clone, SyntaxTree)]
+#[TokenSet(TestToken)]
+#[name = "term"]
+#[memo]
+pub enum Term {
+    Mul(Mul),
+    Lit(TokenLit),
+}
+
+#[derive(Debug, Clone, SyntaxTree)]
+#[TokenSet(TestToken)]
+pub struct Mul {
+    id: SyntaxTreeId,
+    lhs: Box<Term>,
+    star: TokenStar,
+    rhs: TokenLit,
+}
+
+#[derive(Debug, SyntaxTree)]
+#[TokenSet(TestToken)]
+pub struct Stmt {
+    id: SyntaxTreeId,
+    expr: Expr,
+    semi: TokenSemi,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use neco_syn::{ParserResult, SyntaxTreeIdManager, Tokens};
+
+    fn tokens(s: &str) -> Tokens<TestToken> {
+        let mut ids = SyntaxTreeIdManager::new();
+        let mut res = vec![];
+        for c in s.chars() {
+            let id = ids.create();
+            let span = Span::new();
+            res.push(match c {
+                '+' => TestToken::Plus(TokenPlus { id, span }),
+                '*' => TestToken::Star(TokenStar { id, span }),
+                ';' => TestToken::Semi(TokenSemi { id, span }),
+                c => TestToken::Lit(TokenLit { id, span, c }),
+            });
+        }
+        Tokens::new(res, ids)
+    }
+
+    fn show_expr(expr: &Expr) -> String {
+        match expr {
+            Expr::Add(add) => format!("({}+{})", show_expr(&add.lhs), show_term(&add.rhs)),
+            Expr::Term(term) => show_term(term),
+        }
+    }
+
+    fn show_term(term: &Term) -> String {
+        match term {
+            Term::Mul(mul) => format!("({}*{})", show_term(&mul.lhs), mul.rhs.c),
+            Term::Lit(lit) => lit.c.to_string(),
+        }
+    }
+
+    #[test]
+    fn test004_left_recursion_1() {
+        let mut tokens = tokens("1;");
+        let t = tokens.parse::<Stmt>();
+        if let ParserResult::Ok(stmt) = t {
+            assert_eq!(show_expr(&stmt.expr), "1");
+        } else {
+            panic!();
+        }
+        assert_eq!(tokens.get_i(), 2);
+    }
+
+    #[test]
+    fn test004_left_recursion_2() {
+        let mut tokens = tokens("1+2*3*4+5;");
+        let t = tokens.parse::<Stmt>();
+        if let ParserResult::Ok(stmt) = t {
+            assert_eq!(show_expr(&stmt.expr), "((1+((2*3)*4))+5)");
+        } else {
+            panic!();
+        }
+        assert_eq!(tokens.get_i(), 10);
+    }
+
+    #[test]
+    fn test004_left_recursion_fail() {
+        let mut tokens = tokens("1+;");
+        let t = tokens.parse::<Stmt>();
+        let e = t.error().unwrap();
+        assert_eq!(e.i(), 1);
+        assert!(e.expected().contains("TokenSemi"));
+    }
+
+    #[test]
+    fn test004_left_recursion_seed_name() {
+        let mut tokens = tokens(";");
+        let t = tokens.parse::<Stmt>();
+        let e = t.error().unwrap();
+        assert_eq!(e.i(), 0);
+        // the seeds of the left recursions are named like the other expected trees
+        assert_eq!(
+            e.expected().iter().copied().collect::<Vec<_>>(),
+            vec!["Expr", "TokenLit", "term"]
+        );
+    }
+}
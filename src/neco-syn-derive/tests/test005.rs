@@ -0,0 +1,142 @@
+use neco_syn::{Associativity, Infix, InfixOperator, Span, SyntaxTreeId};
+use neco_syn_derive::*;
+
+#[derive(Debug, Clone, Token)]
+pub struct TokenLit {
+    id: SyntaxTreeId,
+    span: Span,
+    c: char,
+}
+
+#[derive(Debug, Clone, Token)]
+pub struct TokenPlus {
+    id: SyntaxTreeId,
+    span: Span,
+}
+
+#[derive(Debug, Clone, Token)]
+pub struct TokenStar {
+    id: SyntaxTreeId,
+    span: Span,
+}
+
+#[derive(Debug, Clone, Token)]
+pub struct TokenCaret {
+    id: SyntaxTreeId,
+    span: Span,
+}
+
+#[derive(Debug, Clone, Token)]
+pub struct TokenSemi {
+    id: SyntaxTreeId,
+    span: Span,
+}
+
+#[derive(Clone, TokenSet)]
+pub enum TestToken {
+    Lit(TokenLit),
+    Plus(TokenPlus),
+    Star(TokenStar),
+    Caret(TokenCaret),
+    Semi(TokenSemi),
+}
+
+#[derive(Debug, Clone, SyntaxTree)]
+#[TokenSet(TestToken)]
+pub enum BinOp {
+    Add(TokenPlus),
+    Mul(TokenStar),
+    Pow(TokenCaret),
+}
+
+impl InfixOperator for BinOp {
+    fn precedence(&self) -> usize {
+        match self {
+            BinOp::Add(_) => 1,
+            BinOp::Mul(_) => 2,
+            BinOp::Pow(_) => 3,
+        }
+    }
+    fn associativity(&self) -> Associativity {
+        match self {
+            BinOp::Add(_) | BinOp::Mul(_) => Associativity::Left,
+            BinOp::Pow(_) => Associativity::Right,
+        }
+    }
+}
+
+#[derive(Debug, SyntaxTree)]
+#[TokenSet(TestToken)]
+pub struct Stmt {
+    id: SyntaxTreeId,
+    expr: Infix<TokenLit, BinOp>,
+    semi: TokenSemi,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use neco_syn::{ParserResult, SyntaxTreeIdManager, Tokens};
+
+    fn tokens(s: &str) -> Tokens<TestToken> {
+        let mut ids = SyntaxTreeIdManager::new();
+        let mut res = vec![];
+        for c in s.chars() {
+            let id = ids.create();
+            let span = Span::new();
+            res.push(match c {
+                '+' => TestToken::Plus(TokenPlus { id, span }),
+                '*' => TestToken::Star(TokenStar { id, span }),
+                '^' => TestToken::Caret(TokenCaret { id, span }),
+                ';' => TestToken::Semi(TokenSemi { id, span }),
+                c => TestToken::Lit(TokenLit { id, span, c }),
+            });
+        }
+        Tokens::new(res, ids)
+    }
+
+    fn show(expr: &Infix<TokenLit, BinOp>) -> String {
+        match expr {
+            Infix::Operand(lit) => lit.c.to_string(),
+            Infix::Binary { lhs, op, rhs, .. } => {
+                let op = match op {
+                    BinOp::Add(_) => '+',
+                    BinOp::Mul(_) => '*',
+                    BinOp::Pow(_) => '^',
+                };
+                format!("({}{}{})", show(lhs), op, show(rhs))
+            }
+        }
+    }
+
+    fn parse(s: &str) -> String {
+        let mut tokens = tokens(s);
+        match tokens.parse::<Stmt>() {
+            ParserResult::Ok(stmt) => show(&stmt.expr),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn test005_infix_precedence() {
+        assert_eq!(parse("1+2*3;"), "(1+(2*3))");
+        assert_eq!(parse("1*2+3;"), "((1*2)+3)");
+        assert_eq!(parse("1+2*3^4^5+6;"), "((1+(2*(3^(4^5))))+6)");
+    }
+
+    #[test]
+    fn test005_infix_associativity() {
+        assert_eq!(parse("1+2+3;"), "((1+2)+3)");
+        assert_eq!(parse("1^2^3;"), "(1^(2^3))");
+    }
+
+    #[test]
+    fn test005_infix_fail() {
+        let mut tokens = tokens("1+*;");
+        let t = tokens.parse::<Stmt>();
+        let e = t.error().unwrap();
+        assert_eq!(e.i(), 2);
+        assert!(e.expected().contains("TokenLit"));
+        assert_eq!(tokens.get_i(), 0);
+    }
+}
@@ -0,0 +1,146 @@
+use neco_syn::{Span, SyntaxTreeId};
+use neco_syn_derive::*;
+
+#[derive(Debug, Clone, Token)]
+pub struct TokenLit {
+    id: SyntaxTreeId,
+    span: Span,
+    c: char,
+}
+
+#[derive(Debug, Clone, Token)]
+pub struct TokenEq {
+    id: SyntaxTreeId,
+    span: Span,
+}
+
+#[derive(Debug, Clone, Token)]
+pub struct TokenSemi {
+    id: SyntaxTreeId,
+    span: Span,
+}
+
+#[derive(Clone, TokenSet)]
+pub enum TestToken {
+    Lit(TokenLit),
+    Eq(TokenEq),
+    Semi(TokenSemi),
+}
+
+// x = y
+#[derive(Debug, SyntaxTree)]
+#[TokenSet(TestToken)]
+pub struct Assign(SyntaxTreeId, TokenLit, TokenEq, TokenLit);
+
+#[derive(Debug, SyntaxTree)]
+#[TokenSet(TestToken)]
+pub enum Stmt {
+    // x = y ;
+    Assign(SyntaxTreeId, Assign, TokenSemi),
+    // x ;
+    Expr {
+        id: SyntaxTreeId,
+        lit: TokenLit,
+        semi: TokenSemi,
+    },
+    // ;
+    Empty(TokenSemi),
+    // = x
+    Eq(SyntaxTreeId, TokenEq, TokenLit),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use neco_syn::{ParserResult, SyntaxTree, SyntaxTreeIdManager, Tokens};
+
+    fn make_tokens(s: &str) -> Tokens<TestToken> {
+        let mut ids = SyntaxTreeIdManager::new();
+        let mut res = vec![];
+        for c in s.chars() {
+            let id = ids.create();
+            let span = Span::new();
+            res.push(match c {
+                '=' => TestToken::Eq(TokenEq { id, span }),
+                ';' => TestToken::Semi(TokenSemi { id, span }),
+                c => TestToken::Lit(TokenLit { id, span, c }),
+            });
+        }
+        Tokens::new(res, ids)
+    }
+
+    #[test]
+    fn test006_tuple_struct() {
+        let mut tokens = make_tokens("x=y");
+        let assign = match tokens.parse::<Assign>() {
+            ParserResult::Ok(assign) => assign,
+            _ => panic!(),
+        };
+        assert_eq!(assign.1.c, 'x');
+        assert_eq!(assign.3.c, 'y');
+        assert_eq!(assign.id(), assign.0);
+        assert!(assign.id() != assign.1.id());
+    }
+
+    #[test]
+    fn test006_tuple_struct_fail() {
+        let mut tokens = make_tokens("x==");
+        let e = tokens.parse::<Assign>().error().unwrap().clone();
+        assert_eq!(e.i(), 2);
+        assert_eq!(tokens.get_i(), 0);
+    }
+
+    #[test]
+    fn test006_enum_variants() {
+        let mut tokens = make_tokens("x=y;");
+        match tokens.parse::<Stmt>() {
+            ParserResult::Ok(stmt @ Stmt::Assign(..)) => {
+                if let Stmt::Assign(id, assign, _) = &stmt {
+                    assert_eq!(stmt.id(), *id);
+                    assert_ne!(stmt.id(), assign.id());
+                }
+            }
+            _ => panic!(),
+        }
+
+        let mut tokens = make_tokens("x;");
+        match tokens.parse::<Stmt>() {
+            ParserResult::Ok(stmt @ Stmt::Expr { .. }) => {
+                if let Stmt::Expr { id, lit, .. } = &stmt {
+                    assert_eq!(lit.c, 'x');
+                    assert_eq!(stmt.id(), *id);
+                }
+            }
+            _ => panic!(),
+        }
+
+        let mut tokens = make_tokens(";");
+        assert!(matches!(
+            tokens.parse::<Stmt>(),
+            ParserResult::Ok(Stmt::Empty(_))
+        ));
+
+        let mut tokens = make_tokens("=x");
+        match tokens.parse::<Stmt>() {
+            ParserResult::Ok(stmt @ Stmt::Eq(..)) => {
+                if let Stmt::Eq(id, _, lit) = &stmt {
+                    assert_eq!(lit.c, 'x');
+                    assert_eq!(stmt.id(), *id);
+                }
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn test006_enum_variants_fail() {
+        let mut tokens = make_tokens("x=;");
+        let e = tokens.parse::<Stmt>().error().unwrap().clone();
+        assert_eq!(e.i(), 2);
+        assert_eq!(
+            e.expected().iter().copied().collect::<Vec<_>>(),
+            vec!["TokenLit"]
+        );
+        assert_eq!(tokens.get_i(), 0);
+    }
+}
@@ -0,0 +1,57 @@
+use neco_syn::{Span, SyntaxTreeId};
+use neco_syn_derive::*;
+
+/// A literal token.
+#[derive(Debug, Clone, Token)]
+#[allow(dead_code)]
+pub struct TokenLit {
+    /// The id of the token.
+    id: SyntaxTreeId,
+    span: Span,
+    c: char,
+}
+
+/// The tokens of the test language.
+#[derive(Clone, TokenSet)]
+#[allow(dead_code)]
+pub enum TestToken {
+    /// A literal.
+    Lit(TokenLit),
+}
+
+/// A literal.
+#[derive(Debug, Clone, SyntaxTree)]
+#[allow(dead_code)]
+#[TokenSet(TestToken)]
+#[memo]
+pub struct Lit {
+    /// The id of the tree.
+    id: SyntaxTreeId,
+    #[allow(dead_code)]
+    lit: TokenLit,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use neco_syn::{ParserResult, SyntaxTreeIdManager, Tokens};
+
+    #[test]
+    fn test007_other_attributes() {
+        let mut ids = SyntaxTreeIdManager::new();
+        let id = ids.create();
+        let token = TestToken::Lit(TokenLit {
+            id,
+            span: Span::new(),
+            c: 'a',
+        });
+        let mut tokens = Tokens::new(vec![token], ids);
+        assert!(matches!(tokens.parse::<Lit>(), ParserResult::Ok(_)));
+    }
+
+    #[test]
+    fn test007_ui() {
+        let t = trybuild::TestCases::new();
+        t.compile_fail("tests/ui/*.rs");
+    }
+}
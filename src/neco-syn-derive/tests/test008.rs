@@ -0,0 +1,168 @@
+use neco_syn::{Span, SyntaxTreeId};
+use neco_syn_derive::*;
+use std::marker::PhantomData;
+
+#[derive(Debug, Clone, Token)]
+pub struct TokenWord {
+    id: SyntaxTreeId,
+    span: Span,
+    #[text]
+    word: String,
+}
+
+#[derive(Debug, Clone, Token)]
+pub struct TokenSemi {
+    id: SyntaxTreeId,
+    span: Span,
+}
+
+#[derive(Debug, Clone, Token)]
+pub struct TokenEnd {
+    id: SyntaxTreeId,
+    span: Span,
+}
+
+#[derive(Clone, TokenSet)]
+pub enum TestToken {
+    Word(TokenWord),
+    Semi(TokenSemi),
+    End(TokenEnd),
+}
+
+// a word which is not `let`
+#[derive(Debug, SyntaxTree)]
+#[TokenSet(TestToken)]
+pub struct Name {
+    id: SyntaxTreeId,
+    #[not]
+    #[token("let")]
+    not_let: PhantomData<TokenWord>,
+    word: TokenWord,
+}
+
+// let x ;
+#[derive(Debug, SyntaxTree)]
+#[TokenSet(TestToken)]
+pub struct Let {
+    id: SyntaxTreeId,
+    #[token("let")]
+    #[cut]
+    keyword: TokenWord,
+    name: Name,
+    semi: TokenSemi,
+}
+
+#[derive(Debug, SyntaxTree)]
+#[TokenSet(TestToken)]
+pub enum Stmt {
+    Let(Let),
+    Expr(SyntaxTreeId, Name, TokenSemi),
+}
+
+// the name just before the end
+#[derive(Debug, SyntaxTree)]
+#[TokenSet(TestToken)]
+pub struct Last {
+    id: SyntaxTreeId,
+    name: Name,
+    #[lookahead]
+    end: TokenEnd,
+    #[skip]
+    uses: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use neco_syn::{ParserResult, SyntaxTreeIdManager, Tokens};
+
+    fn make_tokens(s: &str) -> Tokens<TestToken> {
+        let mut ids = SyntaxTreeIdManager::new();
+        let mut res = vec![];
+        for word in s.split_whitespace() {
+            let id = ids.create();
+            let span = Span::new();
+            res.push(match word {
+                ";" => TestToken::Semi(TokenSemi { id, span }),
+                "$" => TestToken::End(TokenEnd { id, span }),
+                word => TestToken::Word(TokenWord {
+                    id,
+                    span,
+                    word: word.to_string(),
+                }),
+            });
+        }
+        Tokens::new(res, ids)
+    }
+
+    #[test]
+    fn test008_token() {
+        let mut tokens = make_tokens("let x ; $");
+        match tokens.parse::<Stmt>() {
+            ParserResult::Ok(Stmt::Let(stmt)) => {
+                assert_eq!(stmt.keyword.word, "let");
+                assert_eq!(stmt.name.word.word, "x");
+            }
+            _ => panic!(),
+        }
+        assert_eq!(tokens.get_i(), 3);
+
+        let mut tokens = make_tokens("x ; $");
+        assert!(matches!(
+            tokens.parse::<Stmt>(),
+            ParserResult::Ok(Stmt::Expr(..))
+        ));
+
+        let mut tokens = make_tokens("fn x ; $");
+        let e = tokens.parse::<Let>().error().unwrap().clone();
+        assert_eq!(e.i(), 0);
+        assert_eq!(
+            e.expected().iter().copied().collect::<Vec<_>>(),
+            vec!["let"]
+        );
+        assert_eq!(tokens.get_i(), 0);
+    }
+
+    #[test]
+    fn test008_not() {
+        let mut tokens = make_tokens("let $");
+        let e = tokens.parse::<Name>().error().unwrap().clone();
+        assert_eq!(e.i(), 0);
+        assert_eq!(
+            e.expected().iter().copied().collect::<Vec<_>>(),
+            vec!["not let"]
+        );
+        assert_eq!(tokens.get_i(), 0);
+    }
+
+    #[test]
+    fn test008_cut() {
+        // `let` commits to Let, so Expr is not tried
+        let mut tokens = make_tokens("let let ; $");
+        let res = tokens.parse::<Stmt>();
+        assert!(res.is_err());
+        assert_eq!(res.error().unwrap().i(), 1);
+
+        let mut tokens = make_tokens("let x $");
+        let res = tokens.parse::<Stmt>();
+        assert!(res.is_err());
+        assert_eq!(res.error().unwrap().i(), 2);
+    }
+
+    #[test]
+    fn test008_lookahead_and_skip() {
+        let mut tokens = make_tokens("x $");
+        match tokens.parse::<Last>() {
+            ParserResult::Ok(last) => {
+                assert_eq!(last.name.word.word, "x");
+                assert_eq!(last.uses, 0);
+            }
+            _ => panic!(),
+        }
+        assert_eq!(tokens.get_i(), 1);
+
+        let mut tokens = make_tokens("x ; $");
+        assert!(tokens.parse::<Last>().is_fail());
+        assert_eq!(tokens.get_i(), 0);
+    }
+}
@@ -0,0 +1,142 @@
+use neco_syn::{Associativity, Infix, InfixOperator, Span, SyntaxTreeId};
+use neco_syn_derive::*;
+
+#[derive(Debug, Clone, Token)]
+pub struct TokenLit {
+    id: SyntaxTreeId,
+    span: Span,
+    #[text]
+    c: char,
+}
+
+#[derive(Debug, Clone, Token)]
+pub struct TokenSymbol {
+    id: SyntaxTreeId,
+    span: Span,
+    #[text]
+    c: char,
+}
+
+#[derive(Clone, TokenSet)]
+pub enum TestToken {
+    Lit(TokenLit),
+    Symbol(TokenSymbol),
+}
+
+#[derive(Debug, Clone, TokenValue)]
+#[value("+")]
+pub struct SymPlus(TokenSymbol);
+
+#[derive(Debug, Clone, TokenValue)]
+#[value("*")]
+pub struct SymStar {
+    token: TokenSymbol,
+}
+
+#[derive(Debug, Clone, TokenValue)]
+#[value(";")]
+pub struct SymSemi(TokenSymbol);
+
+#[derive(Debug, Clone, TokenValue)]
+#[value("x")]
+pub struct LitX(TokenLit);
+
+#[derive(Debug, Clone, SyntaxTree)]
+#[TokenSet(TestToken)]
+pub enum BinOp {
+    Add(SymPlus),
+    Mul(SymStar),
+}
+
+impl InfixOperator for BinOp {
+    fn precedence(&self) -> usize {
+        match self {
+            BinOp::Add(_) => 1,
+            BinOp::Mul(_) => 2,
+        }
+    }
+    fn associativity(&self) -> Associativity {
+        Associativity::Left
+    }
+}
+
+#[derive(Debug, SyntaxTree)]
+#[TokenSet(TestToken)]
+pub struct Stmt {
+    id: SyntaxTreeId,
+    expr: Infix<TokenLit, BinOp>,
+    semi: SymSemi,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use neco_syn::{ParserResult, SyntaxTree, SyntaxTreeIdManager, TokenValue, Tokens};
+
+    fn make_tokens(s: &str) -> Tokens<TestToken> {
+        let mut ids = SyntaxTreeIdManager::new();
+        let mut res = vec![];
+        for c in s.chars() {
+            let id = ids.create();
+            let span = Span::new();
+            res.push(if c.is_ascii_alphanumeric() {
+                TestToken::Lit(TokenLit { id, span, c })
+            } else {
+                TestToken::Symbol(TokenSymbol { id, span, c })
+            });
+        }
+        Tokens::new(res, ids)
+    }
+
+    fn show(expr: &Infix<TokenLit, BinOp>) -> String {
+        match expr {
+            Infix::Operand(t) => t.c.to_string(),
+            Infix::Binary { lhs, op, rhs, .. } => {
+                let op = match op {
+                    BinOp::Add(_) => SymPlus::VALUE,
+                    BinOp::Mul(_) => SymStar::VALUE,
+                };
+                format!("({}{}{})", show(lhs), op, show(rhs))
+            }
+        }
+    }
+
+    #[test]
+    fn test009_value() {
+        let mut tokens = make_tokens("1+2*3+4;");
+        match tokens.parse::<Stmt>() {
+            ParserResult::Ok(stmt) => assert_eq!(show(&stmt.expr), "((1+(2*3))+4)"),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn test009_value_fail() {
+        let mut tokens = make_tokens("1-2;");
+        let e = tokens.parse::<Stmt>().error().unwrap().clone();
+        assert_eq!(e.i(), 1);
+        assert_eq!(e.expected().iter().copied().collect::<Vec<_>>(), vec![";"]);
+
+        let mut tokens = make_tokens("y");
+        let e = tokens.parse::<LitX>().error().unwrap().clone();
+        assert_eq!(e.i(), 0);
+        assert_eq!(e.expected().iter().copied().collect::<Vec<_>>(), vec!["x"]);
+        assert_eq!(tokens.get_i(), 0);
+
+        // the base token type must match too
+        let mut tokens = make_tokens("+");
+        assert!(tokens.parse::<LitX>().is_fail());
+    }
+
+    #[test]
+    fn test009_base() {
+        let mut tokens = make_tokens("x");
+        let x = match tokens.parse::<LitX>() {
+            ParserResult::Ok(x) => x,
+            _ => panic!(),
+        };
+        assert_eq!(x.base().c, 'x');
+        assert_eq!(SyntaxTree::<TestToken>::id(&x), x.base().id);
+        assert_eq!(tokens.get_i(), 1);
+    }
+}
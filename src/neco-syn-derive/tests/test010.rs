@@ -0,0 +1,121 @@
+use neco_syn::{Span, SyntaxTreeId};
+use neco_syn_derive::*;
+
+#[derive(Debug, Clone, Token)]
+pub struct TokenWord {
+    id: SyntaxTreeId,
+    span: Span,
+    #[text]
+    word: String,
+}
+
+#[derive(Debug, Clone, Token)]
+pub struct TokenOp {
+    id: SyntaxTreeId,
+    span: Span,
+    #[text]
+    op: String,
+}
+
+#[derive(Debug, Clone, Token)]
+pub struct TokenComment {
+    id: SyntaxTreeId,
+    span: Span,
+}
+
+#[derive(Debug, Clone, Token)]
+pub struct TokenSpaces {
+    id: SyntaxTreeId,
+    span: Span,
+}
+
+#[derive(Clone, TokenSet, Lexer)]
+pub enum TestToken {
+    #[pattern("//[^\\n]*")]
+    Comment(TokenComment),
+    #[literal("=")]
+    #[literal("==")]
+    #[literal("/")]
+    Op(TokenOp),
+    #[pattern("\\w+")]
+    Word(TokenWord),
+    #[pattern("[ \\n]+")]
+    Spaces(TokenSpaces),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use neco_syn::{lexer::Lexer, Project, SyntaxTreeIdManager, Token};
+    use std::path::PathBuf;
+
+    fn lex(
+        s: &str,
+    ) -> (
+        Project,
+        Vec<TestToken>,
+        Vec<neco_syn::diagnostic::Diagnostic>,
+    ) {
+        let mut project = Project::new();
+        let id = project.add_file(PathBuf::from("test.txt"), s.chars().collect());
+        let (tokens, diagnostics) = {
+            let file = project.get_file(id).unwrap();
+            TestToken::lex(file, &mut SyntaxTreeIdManager::new())
+        };
+        (project, tokens, diagnostics)
+    }
+
+    fn show(project: &Project, tokens: &[TestToken]) -> Vec<String> {
+        tokens
+            .iter()
+            .map(|t| {
+                let (kind, span) = match t {
+                    TestToken::Comment(t) => ("comment", t.span()),
+                    TestToken::Op(t) => ("op", t.span()),
+                    TestToken::Word(t) => ("word", t.span()),
+                    TestToken::Spaces(t) => ("spaces", t.span()),
+                };
+                format!("{} {}", kind, project.snippet(span).unwrap())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test010_maximal_munch() {
+        let (project, tokens, diagnostics) = lex("a == b / c // d == e\nf=g");
+        assert!(diagnostics.is_empty());
+        assert_eq!(
+            show(&project, &tokens),
+            vec![
+                "word a",
+                "spaces  ",
+                "op ==",
+                "spaces  ",
+                "word b",
+                "spaces  ",
+                "op /",
+                "spaces  ",
+                "word c",
+                "spaces  ",
+                "comment // d == e",
+                "spaces \n",
+                "word f",
+                "op =",
+                "word g",
+            ]
+        );
+        match &tokens[2] {
+            TestToken::Op(t) => assert_eq!(t.op, "=="),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn test010_unknown_character() {
+        let (project, tokens, diagnostics) = lex("a ? b");
+        assert_eq!(tokens.len(), 4);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].message, "unknown character `?`");
+        assert_eq!(project.snippet(diagnostics[0].span).unwrap(), "?");
+    }
+}
@@ -0,0 +1,125 @@
+use neco_syn::{Span, SyntaxTreeId};
+use neco_syn_derive::*;
+
+#[derive(Debug, Clone, Token)]
+pub struct TokenWord {
+    id: SyntaxTreeId,
+    span: Span,
+    #[text]
+    word: String,
+}
+
+#[derive(Debug, Clone, Token)]
+pub struct TokenEq {
+    id: SyntaxTreeId,
+    span: Span,
+}
+
+#[derive(Debug, Clone, Token)]
+pub struct TokenComment {
+    id: SyntaxTreeId,
+    span: Span,
+}
+
+#[derive(Debug, Clone, Token)]
+pub struct TokenSpaces {
+    id: SyntaxTreeId,
+    span: Span,
+}
+
+#[derive(Debug, Clone, TokenSet, Lexer)]
+pub enum TestToken {
+    #[pattern("#[^\\n]*")]
+    #[trivia]
+    Comment(TokenComment),
+    #[literal("=")]
+    Eq(TokenEq),
+    #[pattern("\\w+")]
+    Word(TokenWord),
+    #[pattern("\\s+")]
+    #[trivia]
+    Spaces(TokenSpaces),
+}
+
+#[derive(Debug, SyntaxTree)]
+#[TokenSet(TestToken)]
+pub struct Assign {
+    id: SyntaxTreeId,
+    lhs: TokenWord,
+    eq: TokenEq,
+    rhs: TokenWord,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use neco_syn::{
+        lexer::Lexer, ParserResult, ProgramFileId, Project, SyntaxTreeIdManager, TokenSet, Tokens,
+    };
+    use std::path::PathBuf;
+
+    const SOURCE: &str = "  # x is\n x =\ty # y\n\n";
+
+    fn lex(project: &mut Project) -> (ProgramFileId, Tokens<TestToken>) {
+        let file_id = project.add_file(PathBuf::from("test.txt"), SOURCE.chars().collect());
+        let mut ids = SyntaxTreeIdManager::new();
+        let (tokens, _) = TestToken::lex(project.get_file(file_id).unwrap(), &mut ids);
+        (file_id, Tokens::new(tokens, ids))
+    }
+
+    fn snippets(project: &Project, ts: &[TestToken]) -> Vec<String> {
+        ts.iter()
+            .map(|t| project.snippet(t.span()).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test011_parse_through_trivia() {
+        let mut project = Project::new();
+        let (_, mut tokens) = lex(&mut project);
+        let assign = match tokens.parse::<Assign>() {
+            ParserResult::Ok(assign) => assign,
+            _ => panic!(),
+        };
+        assert_eq!(assign.lhs.word, "x");
+        assert_eq!(assign.rhs.word, "y");
+
+        let trivia = tokens.trivia_of(assign.lhs.id).unwrap();
+        assert_eq!(
+            snippets(&project, &trivia.leading),
+            vec!["  ", "# x is", "\n "]
+        );
+        assert!(trivia.trailing.is_empty());
+        let trivia = tokens.trivia_of(assign.eq.id).unwrap();
+        assert_eq!(snippets(&project, &trivia.leading), vec![" "]);
+        let trivia = tokens.trivia_of(assign.rhs.id).unwrap();
+        assert_eq!(snippets(&project, &trivia.leading), vec!["\t"]);
+        assert_eq!(
+            snippets(&project, &trivia.trailing),
+            vec![" ", "# y", "\n\n"]
+        );
+    }
+
+    #[test]
+    fn test011_source_text() {
+        let mut project = Project::new();
+        let (file_id, tokens) = lex(&mut project);
+        let file = project.get_file(file_id).unwrap();
+        assert_eq!(tokens.source_text(file), SOURCE);
+        assert_eq!(tokens.lossless_tokens().len(), 11);
+    }
+
+    #[test]
+    fn test011_only_trivia() {
+        let mut project = Project::new();
+        let file_id = project.add_file(PathBuf::from("test.txt"), " # a\n".chars().collect());
+        let mut ids = SyntaxTreeIdManager::new();
+        let (tokens, _) = TestToken::lex(project.get_file(file_id).unwrap(), &mut ids);
+        let tokens = Tokens::new(tokens, ids);
+        assert!(tokens.trivia(0).is_none());
+        assert_eq!(
+            tokens.source_text(project.get_file(file_id).unwrap()),
+            " # a\n"
+        );
+    }
+}
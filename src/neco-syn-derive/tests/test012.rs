@@ -0,0 +1,192 @@
+use neco_syn::{
+    Associativity, Infix, InfixOperator, Optional, Punctuated, Rep0, Span, SyntaxTreeId,
+};
+use neco_syn_derive::*;
+
+#[derive(Debug, Clone, Token)]
+pub struct TokenLit {
+    id: SyntaxTreeId,
+    span: Span,
+    c: char,
+}
+
+#[derive(Debug, Clone, Token)]
+pub struct TokenPlus {
+    id: SyntaxTreeId,
+    span: Span,
+}
+
+#[derive(Debug, Clone, Token)]
+pub struct TokenMinus {
+    id: SyntaxTreeId,
+    span: Span,
+}
+
+#[derive(Debug, Clone, Token)]
+pub struct TokenComma {
+    id: SyntaxTreeId,
+    span: Span,
+}
+
+#[derive(Debug, Clone, Token)]
+pub struct TokenSemi {
+    id: SyntaxTreeId,
+    span: Span,
+}
+
+#[derive(Debug, Clone, Token)]
+pub struct TokenEnd {
+    id: SyntaxTreeId,
+    span: Span,
+}
+
+#[derive(Clone, TokenSet)]
+pub enum TestToken {
+    Lit(TokenLit),
+    Plus(TokenPlus),
+    Minus(TokenMinus),
+    Comma(TokenComma),
+    Semi(TokenSemi),
+    End(TokenEnd),
+}
+
+#[derive(Debug, SyntaxTree)]
+#[TokenSet(TestToken)]
+pub enum Term {
+    Lit(TokenLit),
+    Neg(SyntaxTreeId, TokenMinus, Box<Term>),
+}
+
+#[derive(Debug, SyntaxTree)]
+#[TokenSet(TestToken)]
+pub enum BinOp {
+    Add(TokenPlus),
+}
+
+impl InfixOperator for BinOp {
+    fn precedence(&self) -> usize {
+        1
+    }
+    fn associativity(&self) -> Associativity {
+        Associativity::Left
+    }
+}
+
+#[derive(Debug, SyntaxTree)]
+#[TokenSet(TestToken)]
+pub struct Stmt {
+    id: SyntaxTreeId,
+    args: Punctuated<Infix<Term, BinOp>, TokenComma>,
+    semi: TokenSemi,
+}
+
+#[derive(Debug, SyntaxTree)]
+#[TokenSet(TestToken)]
+pub struct Block {
+    id: SyntaxTreeId,
+    stmts: Rep0<Stmt>,
+    last: Optional<Term>,
+    end: TokenEnd,
+    #[skip]
+    depth: usize,
+}
+
+visitor! {
+    pub trait Visit, VisitMut {
+        Block,
+        Stmt,
+        Term,
+        BinOp,
+        TokenLit,
+        TokenPlus,
+        TokenMinus,
+        TokenComma,
+        TokenSemi,
+        TokenEnd,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use neco_syn::{visit::Walk, ParserResult, SyntaxTreeIdManager, Tokens};
+
+    fn parse(s: &str) -> Block {
+        let mut ids = SyntaxTreeIdManager::new();
+        let mut res = vec![];
+        for c in s.chars() {
+            let id = ids.create();
+            let span = Span::new();
+            res.push(match c {
+                '+' => TestToken::Plus(TokenPlus { id, span }),
+                '-' => TestToken::Minus(TokenMinus { id, span }),
+                ',' => TestToken::Comma(TokenComma { id, span }),
+                ';' => TestToken::Semi(TokenSemi { id, span }),
+                '$' => TestToken::End(TokenEnd { id, span }),
+                c => TestToken::Lit(TokenLit { id, span, c }),
+            });
+        }
+        match Tokens::new(res, ids).parse::<Block>() {
+            ParserResult::Ok(block) => block,
+            _ => panic!(),
+        }
+    }
+
+    #[derive(Default)]
+    struct Collect {
+        lits: String,
+        stmts: usize,
+        skip_neg: bool,
+    }
+
+    impl Visit for Collect {
+        fn visit_token_lit(&mut self, node: &TokenLit) {
+            self.lits.push(node.c);
+        }
+        fn visit_stmt(&mut self, node: &Stmt) {
+            self.stmts += 1;
+            node.walk(self);
+        }
+        fn visit_term(&mut self, node: &Term) {
+            if self.skip_neg && matches!(node, Term::Neg(..)) {
+                return;
+            }
+            node.walk(self);
+        }
+    }
+
+    struct Upper;
+
+    impl VisitMut for Upper {
+        fn visit_token_lit_mut(&mut self, node: &mut TokenLit) {
+            node.c = node.c.to_ascii_uppercase();
+        }
+    }
+
+    #[test]
+    fn test012_visit() {
+        let block = parse("a+b,-c;d;e$");
+        let mut collect = Collect::default();
+        collect.visit_block(&block);
+        assert_eq!(collect.lits, "abcde");
+        assert_eq!(collect.stmts, 2);
+        // #[skip] fields keep their default and are not walked
+        assert_eq!(block.depth, 0);
+
+        let mut collect = Collect {
+            skip_neg: true,
+            ..Default::default()
+        };
+        collect.visit_block(&block);
+        assert_eq!(collect.lits, "abde");
+    }
+
+    #[test]
+    fn test012_visit_mut() {
+        let mut block = parse("a+-b;c$");
+        Upper.visit_block_mut(&mut block);
+        let mut collect = Collect::default();
+        collect.visit_block(&block);
+        assert_eq!(collect.lits, "ABC");
+    }
+}
@@ -0,0 +1,147 @@
+use neco_syn::{Associativity, Infix, InfixOperator, Optional, Rep0, Span, SyntaxTreeId};
+use neco_syn_derive::*;
+
+#[derive(Debug, Clone, Token)]
+pub struct TokenWord {
+    id: SyntaxTreeId,
+    span: Span,
+}
+
+#[derive(Debug, Clone, Token)]
+pub struct TokenSymbol {
+    id: SyntaxTreeId,
+    span: Span,
+    #[text]
+    c: char,
+}
+
+#[derive(Debug, Clone, Token)]
+pub struct TokenSpaces {
+    id: SyntaxTreeId,
+    span: Span,
+}
+
+#[derive(Debug, Clone, TokenSet, Lexer)]
+pub enum TestToken {
+    #[pattern("\\w+")]
+    Word(TokenWord),
+    #[pattern("[^\\w\\s]")]
+    Symbol(TokenSymbol),
+    #[pattern("\\s+")]
+    #[trivia]
+    Spaces(TokenSpaces),
+}
+
+#[derive(Debug, Clone, TokenValue)]
+#[value("+")]
+pub struct SymPlus(TokenSymbol);
+
+#[derive(Debug, Clone, TokenValue)]
+#[value(";")]
+pub struct SymSemi(TokenSymbol);
+
+#[derive(Debug, Clone, TokenValue)]
+#[value("{")]
+pub struct SymOpen(TokenSymbol);
+
+#[derive(Debug, Clone, TokenValue)]
+#[value("}")]
+pub struct SymClose(TokenSymbol);
+
+#[derive(Debug, Clone, TokenValue)]
+#[value("!")]
+pub struct SymBang(TokenSymbol);
+
+#[derive(Debug, SyntaxTree)]
+#[TokenSet(TestToken)]
+pub enum BinOp {
+    Add(SymPlus),
+}
+
+impl InfixOperator for BinOp {
+    fn precedence(&self) -> usize {
+        1
+    }
+    fn associativity(&self) -> Associativity {
+        Associativity::Left
+    }
+}
+
+#[derive(Debug, SyntaxTree)]
+#[TokenSet(TestToken)]
+pub enum Stmt {
+    Expr {
+        id: SyntaxTreeId,
+        bang: Optional<SymBang>,
+        expr: Infix<TokenWord, BinOp>,
+        semi: SymSemi,
+    },
+    Block(Block),
+}
+
+#[derive(Debug, SyntaxTree)]
+#[TokenSet(TestToken)]
+pub struct Block {
+    id: SyntaxTreeId,
+    open: SymOpen,
+    stmts: Rep0<Stmt>,
+    close: SymClose,
+    #[lookahead]
+    next: Optional<TokenWord>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use neco_syn::{lexer::Lexer, ParserResult, Project, SyntaxTree, SyntaxTreeIdManager, Tokens};
+    use std::path::PathBuf;
+
+    fn parse(s: &str) -> (Project, Block) {
+        let mut project = Project::new();
+        let file_id = project.add_file(PathBuf::from("test.txt"), s.chars().collect());
+        let mut ids = SyntaxTreeIdManager::new();
+        let (tokens, _) = TestToken::lex(project.get_file(file_id).unwrap(), &mut ids);
+        match Tokens::new(tokens, ids).parse::<Block>() {
+            ParserResult::Ok(block) => (project, block),
+            _ => panic!(),
+        }
+    }
+
+    fn snippet<T: SyntaxTree<TestToken>>(project: &Project, t: &T) -> String {
+        project.snippet(t.span()).unwrap()
+    }
+
+    #[test]
+    fn test013_span() {
+        let (project, block) = parse(" { a + b ;  { } !c; } x");
+        assert_eq!(snippet(&project, &block), "{ a + b ;  { } !c; }");
+        assert_eq!(snippet(&project, &block.stmts), "a + b ;  { } !c;");
+        let stmts = &block.stmts.ts;
+        assert_eq!(snippet(&project, &stmts[0]), "a + b ;");
+        assert_eq!(snippet(&project, &stmts[1]), "{ }");
+        assert_eq!(snippet(&project, &stmts[2]), "!c;");
+        match &stmts[0] {
+            Stmt::Expr { expr, .. } => assert_eq!(snippet(&project, expr), "a + b"),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn test013_empty_span() {
+        let (_, block) = parse("{ a; } x");
+        // an empty repetition is at the next token
+        match &block.stmts.ts[0] {
+            Stmt::Expr { bang, .. } => {
+                assert!(bang.span().is_empty());
+                assert_eq!(bang.span().begin(), 2);
+            }
+            _ => panic!(),
+        }
+
+        let (_, block) = parse("{  } x");
+        assert!(block.stmts.span().is_empty());
+        assert_eq!(block.stmts.span().begin(), 3);
+        assert_eq!(block.span().begin(), 0);
+        assert_eq!(block.span().end(), 4);
+    }
+}
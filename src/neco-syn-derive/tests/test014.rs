@@ -0,0 +1,238 @@
+use neco_syn::{Associativity, Infix, InfixOperator, Punctuated, Rep0, Span, SyntaxTreeId};
+use neco_syn_derive::*;
+
+#[derive(Debug, Clone, Token, ToSource)]
+pub struct TokenIdent {
+    id: SyntaxTreeId,
+    span: Span,
+    #[text]
+    ident: String,
+}
+
+#[derive(Debug, Clone, Token, ToSource)]
+pub struct TokenKeyword {
+    id: SyntaxTreeId,
+    span: Span,
+    #[text]
+    keyword: String,
+}
+
+#[derive(Debug, Clone, Token, ToSource)]
+pub struct TokenNumber {
+    id: SyntaxTreeId,
+    span: Span,
+    #[text]
+    number: String,
+}
+
+#[derive(Debug, Clone, Token, ToSource)]
+pub struct TokenSymbol {
+    id: SyntaxTreeId,
+    span: Span,
+    #[text]
+    c: char,
+}
+
+#[derive(Debug, Clone, Token, ToSource)]
+#[text("$")]
+pub struct TokenEnd {
+    id: SyntaxTreeId,
+    span: Span,
+}
+
+#[derive(Debug, Clone, Token)]
+pub struct TokenSpaces {
+    id: SyntaxTreeId,
+    span: Span,
+}
+
+#[derive(Debug, Clone, TokenSet, Lexer)]
+pub enum TestToken {
+    #[literal("let")]
+    Keyword(TokenKeyword),
+    #[pattern("[a-z]\\w*")]
+    Ident(TokenIdent),
+    #[pattern("\\d+")]
+    Number(TokenNumber),
+    #[literal("$")]
+    End(TokenEnd),
+    #[pattern("[^\\w\\s]")]
+    Symbol(TokenSymbol),
+    #[pattern("\\s+")]
+    #[trivia]
+    Spaces(TokenSpaces),
+}
+
+macro_rules! symbols {
+    ($($name:ident $value:literal)*) => {
+        $(
+            #[derive(Debug, Clone, TokenValue, ToSource)]
+            #[value($value)]
+            pub struct $name(TokenSymbol);
+        )*
+    };
+}
+
+symbols! {
+    SymEq "="
+    SymSemi ";"
+    SymComma ","
+    SymLParen "("
+    SymRParen ")"
+    SymPlus "+"
+    SymStar "*"
+}
+
+#[derive(Debug, Clone, TokenValue, ToSource)]
+#[value("let")]
+pub struct KwLet(TokenKeyword);
+
+#[derive(Debug, SyntaxTree, ToSource)]
+#[TokenSet(TestToken)]
+pub enum BinOp {
+    Add(SymPlus),
+    Mul(SymStar),
+}
+
+impl InfixOperator for BinOp {
+    fn precedence(&self) -> usize {
+        match self {
+            BinOp::Add(_) => 1,
+            BinOp::Mul(_) => 2,
+        }
+    }
+    fn associativity(&self) -> Associativity {
+        Associativity::Left
+    }
+}
+
+pub type Expr = Infix<Term, BinOp>;
+
+#[derive(Debug, SyntaxTree, ToSource)]
+#[TokenSet(TestToken)]
+pub struct Call {
+    id: SyntaxTreeId,
+    name: TokenIdent,
+    open: SymLParen,
+    args: Punctuated<Expr, SymComma>,
+    close: SymRParen,
+}
+
+#[derive(Debug, SyntaxTree, ToSource)]
+#[TokenSet(TestToken)]
+pub enum Term {
+    Call(Call),
+    Ident(TokenIdent),
+    Number(TokenNumber),
+    Paren(SyntaxTreeId, SymLParen, Box<Expr>, SymRParen),
+}
+
+#[derive(Debug, SyntaxTree, ToSource)]
+#[TokenSet(TestToken)]
+pub enum Stmt {
+    Let {
+        id: SyntaxTreeId,
+        kw: KwLet,
+        name: TokenIdent,
+        eq: SymEq,
+        expr: Expr,
+        semi: SymSemi,
+    },
+    Expr(SyntaxTreeId, Expr, SymSemi),
+}
+
+#[derive(Debug, SyntaxTree, ToSource)]
+#[TokenSet(TestToken)]
+pub struct Program {
+    id: SyntaxTreeId,
+    stmts: Rep0<Stmt>,
+    end: TokenEnd,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use neco_syn::{
+        lexer::Lexer,
+        print::{compact_spacing, Printer, ToSource},
+        ParserResult, Project, SyntaxTreeIdManager, Tokens,
+    };
+    use std::path::PathBuf;
+
+    fn parse(s: &str) -> Program {
+        let mut project = Project::new();
+        let file_id = project.add_file(PathBuf::from("test.txt"), s.chars().collect());
+        let mut ids = SyntaxTreeIdManager::new();
+        let (tokens, diagnostics) = TestToken::lex(project.get_file(file_id).unwrap(), &mut ids);
+        assert!(diagnostics.is_empty());
+        match Tokens::new(tokens, ids).parse::<Program>() {
+            ParserResult::Ok(program) => program,
+            _ => panic!(),
+        }
+    }
+
+    fn compact<T: ToSource>(t: &T) -> String {
+        let mut printer = Printer::with_spacing(compact_spacing);
+        t.to_source(&mut printer);
+        printer.finish()
+    }
+
+    #[test]
+    fn test014_print() {
+        let program = parse("let x =f( 1,2 + y )*(3+4) ;\n  print(x);\n$");
+        assert_eq!(
+            program.to_source_string(),
+            "let x = f (1, 2 + y) * (3 + 4); print (x); $"
+        );
+        assert_eq!(compact(&program), "let x=f(1,2+y)*(3+4);print(x);$");
+    }
+
+    #[test]
+    fn test014_round_trip() {
+        for s in &[
+            "$",
+            "a;$",
+            "let a = 1 + 2 * 3; f(); g(a, (a + 1) * 2, h(4));$",
+            "let let_x = (((1)));$",
+        ] {
+            let program = parse(s);
+            let printed = program.to_source_string();
+            let reparsed = parse(&printed);
+            assert_eq!(reparsed.to_source_string(), printed);
+            assert_eq!(compact(&reparsed), compact(&program));
+        }
+    }
+
+    #[test]
+    fn test014_generate() {
+        let mut ids = SyntaxTreeIdManager::new();
+        let span = Span::new();
+        let mut symbol = |c| TokenSymbol {
+            id: ids.create(),
+            span,
+            c,
+        };
+        let open = SymLParen(symbol('('));
+        let close = SymRParen(symbol(')'));
+        let semi = SymSemi(symbol(';'));
+        let call = Call {
+            id: ids.create(),
+            name: TokenIdent {
+                id: ids.create(),
+                span,
+                ident: "main".to_string(),
+            },
+            open,
+            args: Punctuated {
+                id: ids.create(),
+                span,
+                ts: vec![],
+                ps: vec![],
+                errors: vec![],
+            },
+            close,
+        };
+        let stmt = Stmt::Expr(ids.create(), Infix::Operand(Term::Call(call)), semi);
+        assert_eq!(stmt.to_source_string(), "main ();");
+    }
+}
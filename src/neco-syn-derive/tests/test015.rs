@@ -0,0 +1,159 @@
+use neco_syn::{Eof, Optional, Rep0, Span, SyntaxTreeId};
+use neco_syn_derive::*;
+
+#[derive(Debug, Clone, Token)]
+pub struct TokenWord {
+    id: SyntaxTreeId,
+    span: Span,
+}
+
+#[derive(Debug, Clone, Token)]
+pub struct TokenSymbol {
+    id: SyntaxTreeId,
+    span: Span,
+    #[text]
+    c: char,
+}
+
+#[derive(Debug, Clone, Token)]
+pub struct TokenSpaces {
+    id: SyntaxTreeId,
+    span: Span,
+}
+
+#[derive(Debug, Clone, TokenSet, Lexer)]
+pub enum TestToken {
+    #[pattern("\\w+")]
+    Word(TokenWord),
+    #[pattern("[^\\w\\s]")]
+    Symbol(TokenSymbol),
+    #[pattern("\\s+")]
+    #[trivia]
+    Spaces(TokenSpaces),
+}
+
+#[derive(Debug, Clone, TokenValue)]
+#[value(";")]
+pub struct SymSemi(TokenSymbol);
+
+#[derive(Debug, SyntaxTree)]
+#[TokenSet(TestToken)]
+pub struct Stmt {
+    id: SyntaxTreeId,
+    word: TokenWord,
+    semi: Optional<SymSemi>,
+}
+
+#[derive(Debug, SyntaxTree)]
+#[TokenSet(TestToken)]
+pub struct Stmts {
+    id: SyntaxTreeId,
+    stmts: Rep0<Stmt>,
+}
+
+#[derive(Debug, SyntaxTree)]
+#[TokenSet(TestToken)]
+pub struct Pair {
+    id: SyntaxTreeId,
+    word: TokenWord,
+    semi: SymSemi,
+}
+
+#[derive(Debug, SyntaxTree)]
+#[TokenSet(TestToken)]
+pub struct Pairs {
+    id: SyntaxTreeId,
+    pairs: Rep0<Pair>,
+}
+
+#[derive(Debug, SyntaxTree)]
+#[TokenSet(TestToken)]
+pub struct File {
+    id: SyntaxTreeId,
+    stmts: Rep0<Stmt>,
+    eof: Eof,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use neco_syn::{lexer::Lexer, ParserResult, SyntaxTreeIdManager, Tokens};
+
+    fn tokens(s: &str) -> Tokens<TestToken> {
+        let mut project = neco_syn::Project::new();
+        let file_id = project.add_file("test.txt".into(), s.chars().collect());
+        let mut ids = SyntaxTreeIdManager::new();
+        let (tokens, _) = TestToken::lex(project.get_file(file_id).unwrap(), &mut ids);
+        Tokens::new(tokens, ids)
+    }
+
+    fn range(span: Span) -> (usize, usize) {
+        (span.begin(), span.end())
+    }
+
+    #[test]
+    fn test015_end_of_input() {
+        // the optional `;` is missing at the end
+        let mut ts = tokens("a; b");
+        match ts.parse::<Stmts>() {
+            ParserResult::Ok(stmts) => assert_eq!(stmts.stmts.ts.len(), 2),
+            _ => panic!(),
+        }
+        assert!(ts.is_eof());
+        assert!(ts.get_token().is_none());
+
+        match tokens("").parse::<Stmts>() {
+            ParserResult::Ok(stmts) => assert!(stmts.stmts.ts.is_empty()),
+            _ => panic!(),
+        }
+
+        // a token expected at the end is reported at the end of the last token
+        let mut ts = tokens("a ");
+        ts.next();
+        match ts.parse::<TokenWord>() {
+            ParserResult::Fail(e) => assert_eq!(range(e.span()), (1, 1)),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn test015_eof() {
+        match tokens("a; b").parse::<File>() {
+            ParserResult::Ok(file) => {
+                assert_eq!(file.stmts.ts.len(), 2);
+                assert_eq!(range(file.eof.span), (4, 4));
+            }
+            _ => panic!(),
+        }
+        match tokens("a; ;").parse::<File>() {
+            ParserResult::Fail(e) => {
+                assert_eq!(range(e.span()), (3, 4));
+                assert!(e.expected().contains(neco_syn::EOF));
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn test015_parse_complete() {
+        assert!(tokens("a; b").parse_complete::<Stmts>().is_ok());
+        assert!(tokens("").parse_complete::<Stmts>().is_ok());
+
+        let diagnostic = tokens("a; ;")
+            .parse_complete::<Stmts>()
+            .unwrap_err()
+            .to_diagnostic();
+        assert_eq!(range(diagnostic.span), (3, 4));
+        assert_eq!(diagnostic.message, "expected one of `EOF`, `TokenWord`");
+        // the code is up to the language
+        assert_eq!(diagnostic.code, None);
+
+        // the furthest failure wins over the leftover tokens
+        let diagnostic = tokens("a; b c")
+            .parse_complete::<Pairs>()
+            .unwrap_err()
+            .to_diagnostic();
+        assert_eq!(range(diagnostic.span), (5, 6));
+        assert_eq!(diagnostic.message, "expected `;`");
+    }
+}
@@ -0,0 +1,136 @@
+use neco_syn::{Eof, Optional, Rep0, Span, SyntaxTreeId};
+use neco_syn_derive::*;
+
+#[derive(Debug, Clone, Token)]
+pub struct TokenWord {
+    id: SyntaxTreeId,
+    span: Span,
+}
+
+#[derive(Debug, Clone, Token)]
+pub struct TokenSymbol {
+    id: SyntaxTreeId,
+    span: Span,
+    #[text]
+    c: char,
+}
+
+#[derive(Debug, Clone, Token)]
+pub struct TokenSpaces {
+    id: SyntaxTreeId,
+    span: Span,
+}
+
+#[derive(Debug, Clone, TokenSet, Lexer)]
+pub enum TestToken {
+    #[pattern("\\w+")]
+    Word(TokenWord),
+    #[pattern("[^\\w\\s]")]
+    Symbol(TokenSymbol),
+    #[pattern("\\s+")]
+    #[trivia]
+    Spaces(TokenSpaces),
+}
+
+#[derive(Debug, Clone, TokenValue)]
+#[value(";")]
+pub struct SymSemi(TokenSymbol);
+
+#[derive(Debug, Clone, TokenValue)]
+#[value("=")]
+pub struct SymEq(TokenSymbol);
+
+#[derive(Debug, Clone, SyntaxTree)]
+#[TokenSet(TestToken)]
+#[memo]
+pub struct Item {
+    id: SyntaxTreeId,
+    name: TokenWord,
+    value: Optional<ItemValue>,
+    semi: SymSemi,
+}
+
+#[derive(Debug, Clone, SyntaxTree)]
+#[TokenSet(TestToken)]
+pub struct ItemValue {
+    id: SyntaxTreeId,
+    eq: SymEq,
+    value: Rep0<TokenWord>,
+}
+
+#[derive(Debug, SyntaxTree)]
+#[TokenSet(TestToken)]
+pub struct File {
+    id: SyntaxTreeId,
+    items: Rep0<Item>,
+    eof: Eof,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use neco_syn::{
+        incremental::TextEdit, lexer::Lexer, Project, SyntaxTree, SyntaxTreeIdManager, Tokens,
+    };
+    use std::path::PathBuf;
+
+    fn parse(project: &mut Project, s: &str) -> (neco_syn::ProgramFileId, Tokens<TestToken>, File) {
+        let file_id = project.add_file(PathBuf::from("test.txt"), s.chars().collect());
+        let mut ids = SyntaxTreeIdManager::new();
+        let (tokens, _) = TestToken::lex(project.get_file(file_id).unwrap(), &mut ids);
+        let mut tokens = Tokens::new(tokens, ids);
+        let file = tokens.parse_complete::<File>().unwrap();
+        (file_id, tokens, file)
+    }
+
+    fn snippets(project: &Project, file: &File) -> Vec<String> {
+        file.items
+            .ts
+            .iter()
+            .map(|item| project.snippet(item.span()).unwrap())
+            .collect()
+    }
+
+    fn ids(file: &File) -> Vec<SyntaxTreeId> {
+        file.items.ts.iter().map(|item| item.id()).collect()
+    }
+
+    #[test]
+    fn test016_reparse() {
+        let mut project = Project::new();
+        let (file_id, mut tokens, file) = parse(&mut project, "a = x;\nb = y z;\nc;\nd = w;");
+        let old_ids = ids(&file);
+
+        let edit = TextEdit::new(13, 14, "long_name");
+        let program_file = project.edit_file(file_id, &edit).unwrap();
+        tokens.edit(program_file, &edit);
+        let file = tokens.parse_complete::<File>().unwrap();
+        assert_eq!(
+            snippets(&project, &file),
+            vec!["a = x;", "b = y long_name;", "c;", "d = w;"]
+        );
+        // the items not touching the edit are reused, with their spans shifted
+        let new_ids = ids(&file);
+        assert_eq!(new_ids[0], old_ids[0]);
+        assert_ne!(new_ids[1], old_ids[1]);
+        assert_eq!(new_ids[2..], old_ids[2..]);
+
+        // an edit which changes the structure
+        let edit = TextEdit::new(28, 28, "; e");
+        let program_file = project.edit_file(file_id, &edit).unwrap();
+        tokens.edit(program_file, &edit);
+        let file = tokens.parse_complete::<File>().unwrap();
+        assert_eq!(
+            snippets(&project, &file),
+            vec!["a = x;", "b = y long_name;", "c;", "d;", "e = w;"]
+        );
+        assert_eq!(ids(&file)[..3], new_ids[..3]);
+
+        // an edit which breaks the file
+        let edit = TextEdit::new(2, 3, "");
+        let program_file = project.edit_file(file_id, &edit).unwrap();
+        tokens.edit(program_file, &edit);
+        let e = tokens.parse_complete::<File>().unwrap_err();
+        assert_eq!(project.snippet(e.span()).unwrap(), "x");
+    }
+}
@@ -0,0 +1,23 @@
+use neco_syn::{Span, SyntaxTreeId};
+use neco_syn_derive::*;
+
+#[derive(Clone, Token)]
+pub struct TokenLit {
+    id: SyntaxTreeId,
+    span: Span,
+}
+
+#[derive(Clone, TokenSet)]
+pub enum TestToken {
+    Lit(TokenLit),
+}
+
+#[derive(Clone, SyntaxTree)]
+#[TokenSet(TestToken)]
+#[recover(TokenLit, until = TokenLit)]
+pub struct Lit {
+    id: SyntaxTreeId,
+    lit: TokenLit,
+}
+
+fn main() {}
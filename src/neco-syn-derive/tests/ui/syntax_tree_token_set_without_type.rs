@@ -0,0 +1,22 @@
+use neco_syn::{Span, SyntaxTreeId};
+use neco_syn_derive::*;
+
+#[derive(Clone, Token)]
+pub struct TokenLit {
+    id: SyntaxTreeId,
+    span: Span,
+}
+
+#[derive(Clone, TokenSet)]
+pub enum TestToken {
+    Lit(TokenLit),
+}
+
+#[derive(SyntaxTree)]
+#[TokenSet]
+pub struct Lit {
+    id: SyntaxTreeId,
+    lit: TokenLit,
+}
+
+fn main() {}
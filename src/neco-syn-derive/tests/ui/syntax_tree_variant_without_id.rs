@@ -0,0 +1,28 @@
+use neco_syn::{Span, SyntaxTreeId};
+use neco_syn_derive::*;
+
+#[derive(Clone, Token)]
+pub struct TokenLit {
+    id: SyntaxTreeId,
+    span: Span,
+}
+
+#[derive(Clone, Token)]
+pub struct TokenSemi {
+    id: SyntaxTreeId,
+    span: Span,
+}
+
+#[derive(Clone, TokenSet)]
+pub enum TestToken {
+    Lit(TokenLit),
+    Semi(TokenSemi),
+}
+
+#[derive(SyntaxTree)]
+#[TokenSet(TestToken)]
+pub enum Stmt {
+    Expr(TokenLit, TokenSemi),
+}
+
+fn main() {}
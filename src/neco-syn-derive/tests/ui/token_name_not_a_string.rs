@@ -0,0 +1,11 @@
+use neco_syn::{Span, SyntaxTreeId};
+use neco_syn_derive::*;
+
+#[derive(Clone, Token)]
+#[name(identifier)]
+pub struct TokenIdent {
+    id: SyntaxTreeId,
+    span: Span,
+}
+
+fn main() {}
@@ -0,0 +1,20 @@
+use neco_syn::{Span, SyntaxTreeId};
+use neco_syn_derive::*;
+
+#[derive(Clone, Token)]
+pub struct TokenLit {
+    id: SyntaxTreeId,
+    span: Span,
+}
+
+#[derive(Clone, TokenSet)]
+pub enum TestToken {
+    Lit(TokenLit),
+}
+
+#[derive(Clone, TokenSet)]
+pub enum Tokens {
+    Lit(TokenLit, TokenLit),
+}
+
+fn main() {}
@@ -0,0 +1,444 @@
+use crate::{ParseError, Project, Span};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Note => write!(f, "note"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+// replace the text of span with replacement
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Suggestion {
+    pub span: Span,
+    pub replacement: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: Option<String>,
+    pub message: String,
+    pub span: Span,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+    pub suggestions: Vec<Suggestion>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, message: String, span: Span) -> Diagnostic {
+        Diagnostic {
+            severity,
+            code: None,
+            message,
+            span,
+            labels: vec![],
+            notes: vec![],
+            suggestions: vec![],
+        }
+    }
+    pub fn error(message: String, span: Span) -> Diagnostic {
+        Diagnostic::new(Severity::Error, message, span)
+    }
+    pub fn warning(message: String, span: Span) -> Diagnostic {
+        Diagnostic::new(Severity::Warning, message, span)
+    }
+    pub fn with_code(mut self, code: &str) -> Diagnostic {
+        self.code = Some(code.to_string());
+        self
+    }
+    pub fn with_label(mut self, span: Span, message: String) -> Diagnostic {
+        self.labels.push(Label { span, message });
+        self
+    }
+    pub fn with_note(mut self, note: String) -> Diagnostic {
+        self.notes.push(note);
+        self
+    }
+    pub fn with_suggestion(
+        mut self,
+        span: Span,
+        replacement: String,
+        message: String,
+    ) -> Diagnostic {
+        self.suggestions.push(Suggestion {
+            span,
+            replacement,
+            message,
+        });
+        self
+    }
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+    // rustc style message with source excerpts. spans which are not in the project are skipped.
+    pub fn render(&self, project: &Project) -> String {
+        let mut res = String::new();
+        match &self.code {
+            Some(code) => res.push_str(&format!("{}[{}]: {}\n", self.severity, code, self.message)),
+            None => res.push_str(&format!("{}: {}\n", self.severity, self.message)),
+        }
+        let mut annotations = vec![Annotation {
+            span: self.span,
+            mark: '^',
+            message: "",
+        }];
+        for label in &self.labels {
+            annotations.push(Annotation {
+                span: label.span,
+                mark: '-',
+                message: &label.message,
+            });
+        }
+        let width = annotations
+            .iter()
+            .filter_map(|a| project.location(a.span))
+            .map(|location| location.end.line.to_string().len())
+            .max()
+            .unwrap_or(0);
+        let mut done = vec![];
+        let mut rendered = 0;
+        for annotation in &annotations {
+            let program_file_id = annotation.span.program_file_id();
+            if done.contains(&program_file_id) {
+                continue;
+            }
+            done.push(program_file_id);
+            let location = match project.location(annotation.span) {
+                Some(location) => location,
+                None => continue,
+            };
+            rendered += 1;
+            let arrow = if rendered == 1 { "-->" } else { ":::" };
+            res.push_str(&format!("{:w$}{} {}\n", "", arrow, location, w = width));
+            res.push_str(&format!("{:w$} |\n", "", w = width));
+            let in_file: Vec<_> = annotations
+                .iter()
+                .filter(|a| a.span.program_file_id() == program_file_id)
+                .collect();
+            res.push_str(&render_file(project, &in_file, width));
+        }
+        if rendered > 0 {
+            res.push_str(&format!("{:w$} |\n", "", w = width));
+        }
+        for note in &self.notes {
+            res.push_str(&format!("{:w$} = note: {}\n", "", note, w = width));
+        }
+        for suggestion in &self.suggestions {
+            res.push_str(&format!(
+                "{:w$} = help: {}: `{}`\n",
+                "",
+                suggestion.message,
+                suggestion.replacement,
+                w = width
+            ));
+        }
+        res
+    }
+    // one line of JSON. paths and line/column ranges are null for spans which are not in the project.
+    pub fn to_json(&self, project: &Project) -> String {
+        let code = match &self.code {
+            Some(code) => json_string(code),
+            None => "null".to_string(),
+        };
+        let labels: Vec<_> = self
+            .labels
+            .iter()
+            .map(|label| {
+                format!(
+                    "{{\"span\":{},\"message\":{}}}",
+                    json_span(project, label.span),
+                    json_string(&label.message)
+                )
+            })
+            .collect();
+        let notes: Vec<_> = self.notes.iter().map(|note| json_string(note)).collect();
+        let suggestions: Vec<_> = self
+            .suggestions
+            .iter()
+            .map(|suggestion| {
+                format!(
+                    "{{\"span\":{},\"replacement\":{},\"message\":{}}}",
+                    json_span(project, suggestion.span),
+                    json_string(&suggestion.replacement),
+                    json_string(&suggestion.message)
+                )
+            })
+            .collect();
+        format!(
+            "{{\"severity\":{},\"code\":{},\"message\":{},\"span\":{},\"labels\":[{}],\"notes\":[{}],\"suggestions\":[{}]}}",
+            json_string(&self.severity.to_string()),
+            code,
+            json_string(&self.message),
+            json_span(project, self.span),
+            labels.join(","),
+            notes.join(","),
+            suggestions.join(",")
+        )
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut res = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => res.push_str("\\\""),
+            '\\' => res.push_str("\\\\"),
+            '\n' => res.push_str("\\n"),
+            '\r' => res.push_str("\\r"),
+            '\t' => res.push_str("\\t"),
+            c if (c as u32) < 0x20 => res.push_str(&format!("\\u{:04x}", c as u32)),
+            c => res.push(c),
+        }
+    }
+    res.push('"');
+    res
+}
+
+fn json_span(project: &Project, span: Span) -> String {
+    match project.location(span) {
+        Some(location) => format!(
+            "{{\"path\":{},\"begin\":{},\"end\":{},\"line_begin\":{},\"column_begin\":{},\"line_end\":{},\"column_end\":{}}}",
+            json_string(&location.path.to_string_lossy()),
+            span.begin(),
+            span.end(),
+            location.begin.line,
+            location.begin.column,
+            location.end.line,
+            location.end.column
+        ),
+        None => format!(
+            "{{\"path\":null,\"begin\":{},\"end\":{},\"line_begin\":null,\"column_begin\":null,\"line_end\":null,\"column_end\":null}}",
+            span.begin(),
+            span.end()
+        ),
+    }
+}
+
+struct Annotation<'a> {
+    span: Span,
+    mark: char,
+    message: &'a str,
+}
+
+fn render_file(project: &Project, annotations: &[&Annotation], width: usize) -> String {
+    let file = project
+        .get_file(annotations[0].span.program_file_id())
+        .unwrap();
+    // (line, begin column, end column, mark, message) with 1-origin columns
+    let mut marks = vec![];
+    for annotation in annotations {
+        let begin = file.line_column(annotation.span.begin());
+        // a span which ends just after a line break ends on the line of the break
+        let (span_begin, span_end) = (annotation.span.begin(), annotation.span.end());
+        let end = if span_end > span_begin && file.body().get(span_end - 1) == Some(&'\n') {
+            file.line_column(span_end - 1)
+        } else {
+            file.line_column(span_end)
+        };
+        for line in begin.line..=end.line {
+            let len = file.line(line).map_or(0, |line| line.len());
+            let column_begin = if line == begin.line { begin.column } else { 1 };
+            let column_end = if line == end.line {
+                end.column.min(len + 1)
+            } else {
+                len + 1
+            };
+            let column_end = column_end.max(column_begin + 1);
+            let message = if line == end.line {
+                annotation.message
+            } else {
+                ""
+            };
+            marks.push((line, column_begin, column_end, annotation.mark, message));
+        }
+    }
+    let mut lines: Vec<_> = marks.iter().map(|m| m.0).collect();
+    lines.sort_unstable();
+    lines.dedup();
+    let mut res = String::new();
+    let mut prev = None;
+    for line in lines {
+        if let Some(prev) = prev {
+            if line > prev + 1 {
+                res.push_str("...\n");
+            }
+        }
+        prev = Some(line);
+        let text: String = file.line(line).unwrap_or(&[]).iter().collect();
+        res.push_str(&format!("{:>w$} | {}\n", line, text, w = width));
+        for (_, column_begin, column_end, mark, message) in marks.iter().filter(|m| m.0 == line) {
+            let underline: String = std::iter::repeat_n(*mark, column_end - column_begin).collect();
+            let row = format!(
+                "{:w$} | {:c$}{} {}",
+                "",
+                "",
+                underline,
+                message,
+                w = width,
+                c = column_begin - 1
+            );
+            res.push_str(row.trim_end());
+            res.push('\n');
+        }
+    }
+    res
+}
+
+impl ParseError {
+    // without a code, which belongs to the language, like E0001 of Felis
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        let expected: Vec<_> = self.expected().iter().map(|e| format!("`{}`", e)).collect();
+        let message = if expected.len() == 1 {
+            format!("expected {}", expected[0])
+        } else {
+            format!("expected one of {}", expected.join(", "))
+        };
+        Diagnostic::error(message, self.span())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_render_1() {
+        let mut project = Project::new();
+        let id = project.add_file(
+            PathBuf::from("a.felis"),
+            "let x = 1;\nlet y = x +;\n".chars().collect(),
+        );
+        let diagnostic = Diagnostic::error(
+            "expected `TokenNumber`".to_string(),
+            Span::new_with_span(id, 22, 23),
+        )
+        .with_code("E0001")
+        .with_label(
+            Span::new_with_span(id, 4, 5),
+            "`x` is defined here".to_string(),
+        )
+        .with_note("the right hand side of `+` is missing".to_string());
+        let expected = r#"error[E0001]: expected `TokenNumber`
+ --> a.felis:2:12
+  |
+1 | let x = 1;
+  |     - `x` is defined here
+2 | let y = x +;
+  |            ^
+  |
+  = note: the right hand side of `+` is missing
+"#;
+        assert_eq!(diagnostic.render(&project), expected);
+    }
+
+    #[test]
+    fn test_render_multi_line() {
+        let mut project = Project::new();
+        let id = project.add_file(
+            PathBuf::from("a.felis"),
+            "let x =\n  1 +\n  2;\n\n\n\n\n\n\nlet z;\n"
+                .chars()
+                .collect(),
+        );
+        let diagnostic = Diagnostic::warning("unused".to_string(), Span::new_with_span(id, 8, 18))
+            .with_label(Span::new_with_span(id, 25, 30), "here".to_string());
+        let expected = r#"warning: unused
+  --> a.felis:2:1
+   |
+ 2 |   1 +
+   | ^^^^^
+ 3 |   2;
+   | ^^^^
+...
+10 | let z;
+   | ----- here
+   |
+"#;
+        assert_eq!(diagnostic.render(&project), expected);
+    }
+
+    #[test]
+    fn test_render_line_break() {
+        let mut project = Project::new();
+        let id = project.add_file(
+            PathBuf::from("a.felis"),
+            "let x = 1;\r\nlet y = 2;\n\nlet z = 3;\n".chars().collect(),
+        );
+        // the underline stays on the line the span ends with
+        let diagnostic = Diagnostic::error("a".to_string(), Span::new_with_span(id, 4, 12))
+            .with_label(Span::new_with_span(id, 16, 23), "b".to_string());
+        let expected = r#"error: a
+ --> a.felis:1:5
+  |
+1 | let x = 1;
+  |     ^^^^^^
+2 | let y = 2;
+  |     ------ b
+  |
+"#;
+        assert_eq!(diagnostic.render(&project), expected);
+    }
+
+    #[test]
+    fn test_to_json() {
+        let mut project = Project::new();
+        let id = project.add_file(
+            PathBuf::from("a.felis"),
+            "let x = 1\nlet y = \"x\";\n".chars().collect(),
+        );
+        let diagnostic =
+            Diagnostic::error("expected `;`".to_string(), Span::new_with_span(id, 9, 10))
+                .with_code("E0001")
+                .with_label(Span::new_with_span(id, 14, 15), "tab\there".to_string())
+                .with_note("note".to_string())
+                .with_suggestion(
+                    Span::new_with_span(id, 9, 9),
+                    ";".to_string(),
+                    "add `;`".to_string(),
+                );
+        let expected = concat!(
+            r#"{"severity":"error","code":"E0001","message":"expected `;`","#,
+            r#""span":{"path":"a.felis","begin":9,"end":10,"line_begin":1,"column_begin":10,"line_end":2,"column_end":1},"#,
+            r#""labels":[{"span":{"path":"a.felis","begin":14,"end":15,"line_begin":2,"column_begin":5,"line_end":2,"column_end":6},"message":"tab\there"}],"#,
+            r#""notes":["note"],"#,
+            r#""suggestions":[{"span":{"path":"a.felis","begin":9,"end":9,"line_begin":1,"column_begin":10,"line_end":1,"column_end":10},"replacement":";","message":"add `;`"}]}"#,
+        );
+        assert_eq!(diagnostic.to_json(&project), expected);
+    }
+
+    #[test]
+    fn test_to_json_without_file() {
+        let project = Project::new();
+        let diagnostic = Diagnostic::error("cannot read \"x.felis\"".to_string(), Span::new());
+        let expected = concat!(
+            r#"{"severity":"error","code":null,"message":"cannot read \"x.felis\"","#,
+            r#""span":{"path":null,"begin":0,"end":0,"line_begin":null,"column_begin":null,"line_end":null,"column_end":null},"#,
+            r#""labels":[],"notes":[],"suggestions":[]}"#,
+        );
+        assert_eq!(diagnostic.to_json(&project), expected);
+        assert_eq!(
+            diagnostic.render(&project),
+            "error: cannot read \"x.felis\"\n"
+        );
+    }
+}
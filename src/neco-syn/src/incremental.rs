@@ -0,0 +1,220 @@
+// reparsing after an edit of a file.
+//
+// Tokens::edit relexes from the token touching the edit until the new tokens line up with the old ones again.
+// memoized results which did not look at a relexed token are kept, and the ones after the edit are shifted,
+// so parsing the tokens again reuses those subtrees, with their ids. only #[memo] trees are reused.
+
+use crate::{
+    diagnostic::Diagnostic, lexer::Lexer, line_starts, ProgramFile, ProgramFileId, Project,
+    TokenSet, Tokens,
+};
+use std::collections::HashMap;
+
+// replace the chars from begin to end with replacement
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TextEdit {
+    pub begin: usize,
+    pub end: usize,
+    pub replacement: String,
+}
+
+impl TextEdit {
+    pub fn new(begin: usize, end: usize, replacement: &str) -> TextEdit {
+        TextEdit {
+            begin,
+            end,
+            replacement: replacement.to_string(),
+        }
+    }
+    // how much the text after the edit moves
+    pub fn delta(&self) -> isize {
+        self.replacement.chars().count() as isize - (self.end - self.begin) as isize
+    }
+    // the end of the replacement in the edited text
+    pub fn new_end(&self) -> usize {
+        self.begin + self.replacement.chars().count()
+    }
+}
+
+impl ProgramFile {
+    // returns false, without changing anything, if the range is out of the body
+    pub fn edit(&mut self, edit: &TextEdit) -> bool {
+        if edit.begin > edit.end || edit.end > self.body.len() {
+            return false;
+        }
+        self.body
+            .splice(edit.begin..edit.end, edit.replacement.chars());
+        self.line_starts = line_starts(&self.body);
+        true
+    }
+}
+
+impl Project {
+    pub fn edit_file(
+        &mut self,
+        program_file_id: ProgramFileId,
+        edit: &TextEdit,
+    ) -> Option<&ProgramFile> {
+        let file = self.files.get_mut(program_file_id.0.checked_sub(1)?)?;
+        if file.edit(edit) {
+            Some(file)
+        } else {
+            None
+        }
+    }
+}
+
+impl<T: TokenSet + Lexer> Tokens<T> {
+    // update the tokens for an edit. program_file is the file after the edit.
+    // the position is reset, so that the caller can parse again from the beginning.
+    // returns the diagnostics of the relexed part.
+    pub fn edit(&mut self, program_file: &ProgramFile, edit: &TextEdit) -> Vec<Diagnostic> {
+        let delta = edit.delta();
+        let mut old = self.take_lossless_tokens();
+        // a token ending at the edit may be extended by it
+        let first = old
+            .iter()
+            .position(|t| t.span().end() >= edit.begin)
+            .unwrap_or(old.len());
+        let begin = match old.get(first) {
+            Some(t) => t.span().begin().min(edit.begin),
+            None => edit.begin,
+        };
+        // the old tokens after the edit, by where they begin
+        let resync: HashMap<usize, usize> = old
+            .iter()
+            .enumerate()
+            .skip(first)
+            .filter(|(_, t)| t.span().begin() >= edit.end)
+            .map(|(i, t)| (t.span().begin(), i))
+            .collect();
+        let new_end = edit.new_end();
+        let mut last = old.len();
+        let (relexed, diagnostics, _) = T::lex_from(program_file, &mut self.ids, begin, |i| {
+            if i < new_end {
+                return false;
+            }
+            // i is after the edit, so it is at least edit.end in the old text
+            let old_i = i
+                .checked_add_signed(-delta)
+                .expect("offset after the edit is before the start of the file");
+            match resync.get(&old_i) {
+                Some(index) => {
+                    last = *index;
+                    true
+                }
+                None => false,
+            }
+        });
+
+        // the range of the replaced tokens without trivia
+        let kept = old[..first].iter().filter(|t| !t.is_trivia()).count();
+        let removed = old[first..last].iter().filter(|t| !t.is_trivia()).count();
+        let added = relexed.iter().filter(|t| !t.is_trivia()).count();
+        let token_delta = added as isize - removed as isize;
+
+        let mut after = old.split_off(last);
+        old.truncate(first);
+        for t in &mut after {
+            t.shift(delta);
+        }
+        old.extend(relexed);
+        old.extend(after);
+        self.set_tokens(old);
+
+        for ((start, ty), mut entry) in std::mem::take(&mut self.memo) {
+            if entry.reach() <= kept {
+                self.memo.insert((start, ty), entry);
+            } else if start >= kept + removed {
+                entry.shift(token_delta, delta);
+                let start = start
+                    .checked_add_signed(token_delta)
+                    .expect("memo entry after the edit shifted before the first token");
+                self.memo.insert((start, ty), entry);
+            }
+        }
+        self.i = 0;
+        self.errors.clear();
+        self.furthest = None;
+        self.reach.set(0);
+        diagnostics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lang::felis::FelisToken, Span, SyntaxTreeId, SyntaxTreeIdManager};
+    use std::path::PathBuf;
+
+    fn lex(project: &mut Project, s: &str) -> (ProgramFileId, Tokens<FelisToken>) {
+        let file_id = project.add_file(PathBuf::from("test.felis"), s.chars().collect());
+        let mut ids = SyntaxTreeIdManager::new();
+        let (tokens, _) = FelisToken::lex(project.get_file(file_id).unwrap(), &mut ids);
+        (file_id, Tokens::new(tokens, ids))
+    }
+
+    fn spans(tokens: &Tokens<FelisToken>) -> Vec<(usize, usize)> {
+        tokens
+            .lossless_tokens()
+            .iter()
+            .map(|t| (t.span().begin(), t.span().end()))
+            .collect()
+    }
+
+    fn ids(tokens: &Tokens<FelisToken>) -> Vec<SyntaxTreeId> {
+        tokens.lossless_tokens().iter().map(|t| t.id()).collect()
+    }
+
+    // edit, and compare with lexing the edited text from scratch
+    fn check(s: &str, edit: TextEdit, expected: &str) -> (Vec<SyntaxTreeId>, Vec<SyntaxTreeId>) {
+        let mut project = Project::new();
+        let (file_id, mut tokens) = lex(&mut project, s);
+        let old_ids = ids(&tokens);
+        let file = project.edit_file(file_id, &edit).unwrap();
+        assert_eq!(file.text(0, file.body().len()).unwrap(), expected);
+        tokens.edit(file, &edit);
+        assert_eq!(tokens.source_text(file), expected);
+
+        let (_, fresh) = lex(&mut Project::new(), expected);
+        assert_eq!(spans(&tokens), spans(&fresh));
+        (old_ids, ids(&tokens))
+    }
+
+    #[test]
+    fn test_edit_file() {
+        let mut project = Project::new();
+        let (file_id, _) = lex(&mut project, "a\nb");
+        let file = project
+            .edit_file(file_id, &TextEdit::new(1, 2, ";\n\n"))
+            .unwrap();
+        assert_eq!(file.body().iter().collect::<String>(), "a;\n\nb");
+        assert_eq!(file.line_count(), 3);
+        assert!(project
+            .edit_file(file_id, &TextEdit::new(3, 9, ""))
+            .is_none());
+        let span = Span::new_with_span(file_id, 4, 5);
+        assert_eq!(project.snippet(span).unwrap(), "b");
+    }
+
+    #[test]
+    fn test_edit_tokens() {
+        let (old, new) = check("x y = 1; z", TextEdit::new(6, 7, "23"), "x y = 23; z");
+        // tokens before and after the edit keep their ids.
+        // the spaces before `1` are relexed, since the edit touches them.
+        assert_eq!(old[..5], new[..5]);
+        assert_ne!(old[5..7], new[5..7]);
+        assert_eq!(old[7..], new[7..]);
+
+        // the edit joins two tokens
+        let (old, new) = check("ab cd ef", TextEdit::new(2, 3, ""), "abcd ef");
+        assert_eq!(new.len(), 3);
+        assert_eq!(old[3..], new[1..]);
+
+        // the edit splits a token
+        check("abcd ef", TextEdit::new(2, 2, " "), "ab cd ef");
+        check("let x", TextEdit::new(5, 5, " = 1"), "let x = 1");
+        check("let x", TextEdit::new(0, 5, ""), "");
+        check("", TextEdit::new(0, 0, "fn"), "fn");
+    }
+}
@@ -1,159 +1,158 @@
-/*
+pub mod ast;
+pub mod cst;
+pub mod lower;
 
-use neco_table::{Id, MainTable};
+use crate::{
+    diagnostic::Diagnostic, lexer::Lexer, ParseError, ProgramFile, Span, SyntaxTreeId,
+    SyntaxTreeIdManager, Tokens,
+};
+use neco_syn_derive::{Lexer, ToSource, Token, TokenSet};
 
-
-
-#[derive(Debug, Clone)]
-pub struct Project {
-    files: MainTable<ProgramFile>
-}
-
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub enum Token {
+// Keyword comes before Ident, so that a keyword wins over an identifier of the same length.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, TokenSet, Lexer)]
+pub enum FelisToken {
+    #[literal("->")]
+    #[literal("==")]
+    #[literal("!=")]
+    #[literal("<=")]
+    #[literal(">=")]
+    #[pattern("[!-/:-@\\[-`{-~]")]
     Symbol(TokenSymbol),
+    #[literal("fn")]
+    #[literal("let")]
+    #[literal("return")]
+    #[literal("if")]
+    #[literal("else")]
+    #[literal("while")]
+    #[literal("loop")]
+    #[literal("break")]
+    #[literal("continue")]
+    #[literal("true")]
+    #[literal("false")]
+    Keyword(TokenKeyword),
+    #[pattern("[A-Za-z_][A-Za-z0-9_]*")]
     Ident(TokenIdent),
+    #[pattern("[0-9][0-9_]*")]
     Number(TokenNumber),
+    #[pattern("\\s+")]
+    #[trivia]
     Spaces(TokenSpaces),
+    #[pattern("//[^\\n]*")]
+    #[trivia]
+    Comment(TokenComment),
+    // any other character
+    #[pattern(".")]
+    Unknown(TokenUnknown),
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Token, ToSource)]
+#[name = "symbol"]
 pub struct TokenSymbol {
-    pub range: TokenRange,
-    pub c: char,
+    pub id: SyntaxTreeId,
+    pub span: Span,
+    #[text]
+    pub symbol: String,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Token, ToSource)]
+#[name = "identifier"]
 pub struct TokenIdent {
-    pub range: TokenRange,
+    pub id: SyntaxTreeId,
+    pub span: Span,
+    #[text]
     pub ident: String,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Token, ToSource)]
+#[name = "keyword"]
+pub struct TokenKeyword {
+    pub id: SyntaxTreeId,
+    pub span: Span,
+    #[text]
+    pub keyword: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Token, ToSource)]
+#[name = "number"]
 pub struct TokenNumber {
-    pub range: TokenRange,
+    pub id: SyntaxTreeId,
+    pub span: Span,
+    #[text]
     pub number: String,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Token)]
 pub struct TokenSpaces {
-    pub range: TokenRange,
+    pub id: SyntaxTreeId,
+    pub span: Span,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct TokenRange {
-    begin: (usize,usize),
-    end: (usize,usize),
+// a line comment from `//`
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Token)]
+pub struct TokenComment {
+    pub id: SyntaxTreeId,
+    pub span: Span,
 }
 
-impl TokenRange {
-    pub fn new(begin: (usize,usize), end: (usize,usize)) -> TokenRange {
-        TokenRange {
-            begin,
-            end,
-        }
-    }
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Token, ToSource)]
+pub struct TokenUnknown {
+    pub id: SyntaxTreeId,
+    pub span: Span,
+    #[text]
+    pub c: char,
 }
 
-#[derive(Debug, Clone)]
-pub struct Tokens {
-    tokens: Vec<Token>,
+pub fn tokenize(program_file: &ProgramFile, ids: &mut SyntaxTreeIdManager) -> Vec<FelisToken> {
+    // every character is matched by Unknown at least
+    let (tokens, _) = FelisToken::lex(program_file, ids);
+    tokens
 }
 
-impl Tokens {
-    pub fn new(s: &str) -> Tokens {
-        let mut pos_y = 1;
-        let mut pos_x = 1;
-        let mut token_table = MainTable::new();
-        let mut root_tokens = vec![];
-        let cs: Vec<char> = s.chars().collect();
-        let mut i = 0;
-        while i < cs.len() {
-            if cs[i].is_whitespace() {
-                let begin = (pos_y, pos_x);
-                while i < cs.len() {
-                    if cs[i] == '\n' {
-                        pos_y += 1;
-                        pos_x = 1;
-                        i += 1;
-                        continue;
-                    }
-                    if cs[i].is_whitespace() {
-                        pos_x += 1;
-                        i += 1;
-                        continue;
-                    }
-                    break;
-                }
-                let end = (pos_y, pos_x);
-                let node = TokenNode::Spaces(TokenSpaces {
-                    range: TokenRange::new(begin, end),
-                });
-                let id = token_table.insert(node);
-                root_tokens.push(id);
-                continue;
-            }
-            if cs[i].is_ascii_digit() {
-                let begin = (pos_y, pos_x);
-                let mut buf = vec![];
-                while i < cs.len() {
-                    if cs[i].is_ascii_digit() || cs[i] == '_' {
-                        buf.push(cs[i]);
-                        pos_x += 1;
-                        i += 1;
-                        continue;
-                    }
-                    break;
-                }
-                let end = (pos_y, pos_x);
-                let s: String = buf.iter().collect();
-                let node = TokenNode::Number(TokenNumber {
-                    range: TokenRange::new(begin, end),
-                    number: s,
-                });
-                let id = token_table.insert(node);
-                root_tokens.push(id);
-                continue;
-            }
-            if cs[i].is_ascii_alphabetic() || cs[i] == '_' {
-                let begin = (pos_y, pos_x);
-                let mut buf = vec![];
-                while i < cs.len() {
-                    if cs[i].is_ascii_alphanumeric() || cs[i] == '_' {
-                        buf.push(cs[i]);
-                        pos_x += 1;
-                        i += 1;
-                        continue;
-                    }
-                    break;
-                }
-                let end = (pos_y, pos_x);
-                let s: String = buf.iter().collect();
-                let node = TokenNode::Ident(TokenIdent {
-                    range: TokenRange::new(begin, end),
-                    ident: s,
-                });
-                let id = token_table.insert(node);
-                root_tokens.push(id);
-                continue;
-            }
-            {
-                let begin = (pos_y, pos_x);
-                let c = cs[i];
-                pos_x += 1;
-                i += 1;
-                let end = (pos_y, pos_x);
-                let node = TokenNode::Symbol(TokenSymbol {
-                    range: TokenRange::new(begin, end),
-                    c,
-                });
-                let id = token_table.insert(node);
-                root_tokens.push(id);
+pub fn lex_diagnostics(tokens: &[FelisToken]) -> Vec<Diagnostic> {
+    let mut res = vec![];
+    for token in tokens {
+        if let FelisToken::Unknown(t) = token {
+            let mut diagnostic = Diagnostic::error(format!("unknown character `{}`", t.c), t.span)
+                .with_code("E0002");
+            // fullwidth forms of ASCII
+            let c = t.c as u32;
+            if (0xff01..=0xff5e).contains(&c) {
+                let ascii = std::char::from_u32(c - 0xfee0).unwrap();
+                diagnostic = diagnostic.with_suggestion(
+                    t.span,
+                    ascii.to_string(),
+                    "use the ASCII character".to_string(),
+                );
             }
+            res.push(diagnostic);
         }
-        Tokens {
-            token_table,
-            root_tokens,
+    }
+    res
+}
+
+pub fn parse_diagnostic(e: &ParseError) -> Diagnostic {
+    e.to_diagnostic().with_code("E0001")
+}
+
+// tokenize and parse a whole file, recovering from syntax errors to report all of them.
+// the tree is given when the parse reached the end of the file, even with recovered errors.
+pub fn parse(program_file: &ProgramFile) -> (Option<cst::File>, Vec<Diagnostic>) {
+    let mut ids = SyntaxTreeIdManager::new();
+    let tokens = tokenize(program_file, &mut ids);
+    let mut diagnostics = lex_diagnostics(&tokens);
+    // a parse error at an unknown character says nothing new
+    if !diagnostics.is_empty() {
+        return (None, diagnostics);
+    }
+    let mut tokens = Tokens::new(tokens, ids);
+    tokens.set_recovery(true);
+    let res = tokens.parse_complete::<cst::File>();
+    diagnostics.extend(tokens.errors().iter().map(parse_diagnostic));
+    match res {
+        Ok(file) => (Some(file), diagnostics),
+        Err(e) => {
+            diagnostics.push(parse_diagnostic(&e));
+            (None, diagnostics)
         }
     }
 }
@@ -161,46 +160,162 @@ impl Tokens {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{ProgramFileId, Tokens};
+    use std::path::PathBuf;
+
+    fn program_file(s: &str) -> ProgramFile {
+        ProgramFile::new(
+            ProgramFileId(1),
+            PathBuf::from("test.felis"),
+            s.chars().collect(),
+        )
+    }
+
+    fn tokenize_str(s: &str) -> Vec<FelisToken> {
+        tokenize(&program_file(s), &mut SyntaxTreeIdManager::new())
+    }
+
+    fn span(begin: usize, end: usize) -> Span {
+        Span::new_with_span(ProgramFileId(1), begin, end)
+    }
+
+    #[derive(Debug, PartialEq, Eq)]
+    enum Expected {
+        Symbol(Span, String),
+        Ident(Span, String),
+        Keyword(Span, String),
+        Number(Span, String),
+        Spaces(Span),
+        Comment(Span),
+        Unknown(Span, char),
+    }
+
+    fn strip_ids(tokens: &[FelisToken]) -> Vec<Expected> {
+        tokens
+            .iter()
+            .map(|t| match t {
+                FelisToken::Symbol(t) => Expected::Symbol(t.span, t.symbol.clone()),
+                FelisToken::Ident(t) => Expected::Ident(t.span, t.ident.clone()),
+                FelisToken::Keyword(t) => Expected::Keyword(t.span, t.keyword.clone()),
+                FelisToken::Number(t) => Expected::Number(t.span, t.number.clone()),
+                FelisToken::Spaces(t) => Expected::Spaces(t.span),
+                FelisToken::Comment(t) => Expected::Comment(t.span),
+                FelisToken::Unknown(t) => Expected::Unknown(t.span, t.c),
+            })
+            .collect()
+    }
 
     #[test]
-    fn test_tokens_new_1() {
-        let s = r#"1 + 2 * 3"#;
-        let tokens = Tokens::new(s);
-        assert!(tokens.root_tokens.len() == 9);
-        let left: Vec<_> = tokens.root_tokens.iter().map(|x| tokens.token_table.get(*x).unwrap().clone() ).collect();
+    fn test_tokenize_1() {
+        let tokens = tokenize_str("1 + 2 * 3");
+        assert!(tokens.len() == 9);
         let right = vec![
-            TokenNode::Number(TokenNumber{ range: TokenRange::new((1,1),(1,2)), number: "1".to_string() }),
-            TokenNode::Spaces(TokenSpaces{ range: TokenRange::new((1,2),(1,3)) }),
-            TokenNode::Symbol(TokenSymbol{ range: TokenRange::new((1,3),(1,4)), c: '+' }),
-            TokenNode::Spaces(TokenSpaces{ range: TokenRange::new((1,4),(1,5)) }),
-            TokenNode::Number(TokenNumber{ range: TokenRange::new((1,5),(1,6)), number: "2".to_string() }),
-            TokenNode::Spaces(TokenSpaces{ range: TokenRange::new((1,6),(1,7)) }),
-            TokenNode::Symbol(TokenSymbol{ range: TokenRange::new((1,7),(1,8)), c: '*' }),
-            TokenNode::Spaces(TokenSpaces{ range: TokenRange::new((1,8),(1,9)) }),
-            TokenNode::Number(TokenNumber{ range: TokenRange::new((1,9),(1,10)), number: "3".to_string() }),
+            Expected::Number(span(0, 1), "1".to_string()),
+            Expected::Spaces(span(1, 2)),
+            Expected::Symbol(span(2, 3), "+".to_string()),
+            Expected::Spaces(span(3, 4)),
+            Expected::Number(span(4, 5), "2".to_string()),
+            Expected::Spaces(span(5, 6)),
+            Expected::Symbol(span(6, 7), "*".to_string()),
+            Expected::Spaces(span(7, 8)),
+            Expected::Number(span(8, 9), "3".to_string()),
         ];
-        assert_eq!(left, right);
+        assert_eq!(strip_ids(&tokens), right);
     }
 
     #[test]
-    fn test_tokens_new_2() {
-        let s = r#"abc + d_e * _f"#;
-        let tokens = Tokens::new(s);
-        assert!(tokens.root_tokens.len() == 9);
-        let left: Vec<_> = tokens.root_tokens.iter().map(|x| tokens.token_table.get(*x).unwrap().clone() ).collect();
+    fn test_tokenize_2() {
+        let tokens = tokenize_str("abc + d_e * _f");
+        assert!(tokens.len() == 9);
         let right = vec![
-            TokenNode::Ident(TokenIdent{ range: TokenRange::new((1,1),(1,4)), ident: "abc".to_string() }),
-            TokenNode::Spaces(TokenSpaces{ range: TokenRange::new((1,4),(1,5)) }),
-            TokenNode::Symbol(TokenSymbol{ range: TokenRange::new((1,5),(1,6)), c: '+' }),
-            TokenNode::Spaces(TokenSpaces{ range: TokenRange::new((1,6),(1,7)) }),
-            TokenNode::Ident(TokenIdent{ range: TokenRange::new((1,7),(1,10)), ident: "d_e".to_string() }),
-            TokenNode::Spaces(TokenSpaces{ range: TokenRange::new((1,10),(1,11)) }),
-            TokenNode::Symbol(TokenSymbol{ range: TokenRange::new((1,11),(1,12)), c: '*' }),
-            TokenNode::Spaces(TokenSpaces{ range: TokenRange::new((1,12),(1,13)) }),
-            TokenNode::Ident(TokenIdent{ range: TokenRange::new((1,13),(1,15)), ident: "_f".to_string() }),
+            Expected::Ident(span(0, 3), "abc".to_string()),
+            Expected::Spaces(span(3, 4)),
+            Expected::Symbol(span(4, 5), "+".to_string()),
+            Expected::Spaces(span(5, 6)),
+            Expected::Ident(span(6, 9), "d_e".to_string()),
+            Expected::Spaces(span(9, 10)),
+            Expected::Symbol(span(10, 11), "*".to_string()),
+            Expected::Spaces(span(11, 12)),
+            Expected::Ident(span(12, 14), "_f".to_string()),
         ];
-        assert_eq!(left, right);
+        assert_eq!(strip_ids(&tokens), right);
+    }
+
+    #[test]
+    fn test_tokenize_3() {
+        let tokens = tokenize_str("let x =\n  10;");
+        let right = vec![
+            Expected::Keyword(span(0, 3), "let".to_string()),
+            Expected::Spaces(span(3, 4)),
+            Expected::Ident(span(4, 5), "x".to_string()),
+            Expected::Spaces(span(5, 6)),
+            Expected::Symbol(span(6, 7), "=".to_string()),
+            Expected::Spaces(span(7, 10)),
+            Expected::Number(span(10, 12), "10".to_string()),
+            Expected::Symbol(span(12, 13), ";".to_string()),
+        ];
+        assert_eq!(strip_ids(&tokens), right);
+    }
+
+    #[test]
+    fn test_tokenize_keyword_prefix() {
+        let tokens = tokenize_str("fnx fn_ if");
+        let right = vec![
+            Expected::Ident(span(0, 3), "fnx".to_string()),
+            Expected::Spaces(span(3, 4)),
+            Expected::Ident(span(4, 7), "fn_".to_string()),
+            Expected::Spaces(span(7, 8)),
+            Expected::Keyword(span(8, 10), "if".to_string()),
+        ];
+        assert_eq!(strip_ids(&tokens), right);
+    }
+
+    #[test]
+    fn test_tokenize_symbols_and_comment() {
+        let tokens = tokenize_str("->-// a / b\n/");
+        let right = vec![
+            Expected::Symbol(span(0, 2), "->".to_string()),
+            Expected::Symbol(span(2, 3), "-".to_string()),
+            Expected::Comment(span(3, 11)),
+            Expected::Spaces(span(11, 12)),
+            Expected::Symbol(span(12, 13), "/".to_string()),
+        ];
+        assert_eq!(strip_ids(&tokens), right);
+
+        let tokens = tokenize_str("<==!=>");
+        let right = vec![
+            Expected::Symbol(span(0, 2), "<=".to_string()),
+            Expected::Symbol(span(2, 3), "=".to_string()),
+            Expected::Symbol(span(3, 5), "!=".to_string()),
+            Expected::Symbol(span(5, 6), ">".to_string()),
+        ];
+        assert_eq!(strip_ids(&tokens), right);
+    }
+
+    #[test]
+    fn test_tokenize_trivia() {
+        let file = program_file("let x =\n  10;\n");
+        let mut ids = SyntaxTreeIdManager::new();
+        let tokens = Tokens::new(tokenize(&file, &mut ids), ids);
+        // spaces are trivia
+        assert!(tokens.trivia(5).is_none());
+        assert_eq!(tokens.trivia(4).unwrap().trailing.len(), 1);
+        assert_eq!(tokens.source_text(&file), "let x =\n  10;\n");
     }
-}
 
-*/
+    #[test]
+    fn test_tokenize_unknown() {
+        let tokens = tokenize_str("x；λ");
+        let right = vec![
+            Expected::Ident(span(0, 1), "x".to_string()),
+            Expected::Unknown(span(1, 2), '；'),
+            Expected::Unknown(span(2, 3), 'λ'),
+        ];
+        assert_eq!(strip_ids(&tokens), right);
+        let diagnostics = lex_diagnostics(&tokens);
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].message, "unknown character `；`");
+        assert_eq!(diagnostics[0].suggestions[0].replacement, ";");
+        assert!(diagnostics[1].suggestions.is_empty());
+    }
+}
@@ -0,0 +1,165 @@
+// the abstract syntax tree of Felis, lowered from the concrete one.
+//
+// items and expressions are stored in tables of an Ast, and refer to each other by ids.
+// every node keeps the span of its source.
+
+use crate::Span;
+use neco_table::{Id, MainTable};
+
+#[derive(Debug, Clone, Default)]
+pub struct Ast {
+    pub items: MainTable<Item>,
+    pub exprs: MainTable<Expr>,
+    // the items of the file in order
+    pub root: Vec<Id<Item>>,
+}
+
+impl Ast {
+    pub fn new() -> Ast {
+        Ast::default()
+    }
+    // ids are only given by this Ast
+    pub fn item(&self, id: Id<Item>) -> &Item {
+        self.items.get(id).unwrap()
+    }
+    pub fn expr(&self, id: Id<Expr>) -> &Expr {
+        self.exprs.get(id).unwrap()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Ident {
+    pub name: String,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Item {
+    pub kind: ItemKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ItemKind {
+    Fn(FnDef),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FnDef {
+    pub name: Ident,
+    pub params: Vec<Param>,
+    // None for a function which returns nothing
+    pub ret: Option<TypeExpr>,
+    pub body: Block,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Param {
+    pub name: Ident,
+    pub ty: TypeExpr,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Block {
+    pub stmts: Vec<Stmt>,
+    // the value of the block
+    pub tail: Option<Id<Expr>>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Stmt {
+    Let(Let),
+    Return(Return),
+    Assign(Assign),
+    Expr(Id<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Let {
+    pub name: Ident,
+    pub ty: Option<TypeExpr>,
+    pub init: Id<Expr>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Return {
+    pub value: Option<Id<Expr>>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Assign {
+    pub name: Ident,
+    pub value: Id<Expr>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TypeExpr {
+    pub kind: TypeExprKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TypeExprKind {
+    Named(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ExprKind {
+    Literal(Literal),
+    Var(String),
+    Binary {
+        op: BinOp,
+        lhs: Id<Expr>,
+        rhs: Id<Expr>,
+    },
+    Call {
+        callee: Ident,
+        args: Vec<Id<Expr>>,
+    },
+    Block(Block),
+    If {
+        cond: Id<Expr>,
+        then: Block,
+        // a block or another if
+        else_: Option<Id<Expr>>,
+    },
+    While {
+        cond: Id<Expr>,
+        body: Block,
+    },
+    Loop(Block),
+    Break(Option<Id<Expr>>),
+    Continue,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Literal {
+    // the digits without `_`
+    Int(String),
+    Bool(bool),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
@@ -0,0 +1,413 @@
+// the concrete syntax tree of Felis, as parsed from FelisToken.
+//
+// file  := item* EOF
+// item  := "fn" ident "(" (param ("," param)* ","?)? ")" ("->" type)? block
+// param := ident ":" type
+// block := "{" stmt* expr? "}"
+// stmt  := "let" ident (":" type)? "=" expr ";" | "return" expr? ";" | ident "=" expr ";"
+//        | expr ";" | block_expr
+// expr  := term (op term)*
+// term  := number | "true" | "false" | ident "(" (expr ("," expr)* ","?)? ")" | ident
+//        | "(" expr ")" | "break" expr? | "continue" | block_expr
+// block_expr := block | "if" expr block ("else" (block | if))? | "while" expr block | "loop" block
+//
+// a block_expr ends a statement without `;`. lowering makes the last such statement of a block
+// the value of the block, when there is no other.
+//
+// in recovery mode a broken item is skipped up to the next `fn`, and a broken statement up to
+// its `;`. a statement without `;` before the `}` of its block breaks the block instead, since
+// the tail of a block looks like such a statement.
+
+use super::{FelisToken, TokenIdent, TokenKeyword, TokenNumber, TokenSymbol};
+use crate::{Associativity, Eof, Infix, InfixOperator, Optional, Punctuated, Rep0, SyntaxTreeId};
+use neco_syn_derive::{SyntaxTree, ToSource, TokenValue};
+
+macro_rules! token_values {
+    ($base:ident { $($name:ident $value:literal)* }) => {
+        $(
+            #[derive(Debug, Clone, TokenValue, ToSource)]
+            #[value($value)]
+            pub struct $name(pub $base);
+        )*
+    };
+}
+
+token_values! {
+    TokenKeyword {
+        KwFn "fn"
+        KwLet "let"
+        KwReturn "return"
+        KwIf "if"
+        KwElse "else"
+        KwWhile "while"
+        KwLoop "loop"
+        KwBreak "break"
+        KwContinue "continue"
+        KwTrue "true"
+        KwFalse "false"
+    }
+}
+
+token_values! {
+    TokenSymbol {
+        SymLParen "("
+        SymRParen ")"
+        SymLBrace "{"
+        SymRBrace "}"
+        SymColon ":"
+        SymComma ","
+        SymArrow "->"
+        SymSemi ";"
+        SymEq "="
+        SymPlus "+"
+        SymMinus "-"
+        SymStar "*"
+        SymSlash "/"
+        SymEqEq "=="
+        SymNe "!="
+        SymLt "<"
+        SymLe "<="
+        SymGt ">"
+        SymGe ">="
+    }
+}
+
+#[derive(Debug, Clone, SyntaxTree, ToSource)]
+#[TokenSet(FelisToken)]
+pub struct File {
+    pub id: SyntaxTreeId,
+    pub items: Rep0<Item>,
+    pub eof: Eof,
+}
+
+#[derive(Debug, Clone, SyntaxTree, ToSource)]
+#[TokenSet(FelisToken)]
+#[recover(before = KwFn)]
+#[memo]
+pub enum Item {
+    Fn(FnDef),
+}
+
+#[derive(Debug, Clone, SyntaxTree, ToSource)]
+#[TokenSet(FelisToken)]
+pub struct FnDef {
+    pub id: SyntaxTreeId,
+    pub kw_fn: KwFn,
+    pub name: TokenIdent,
+    pub lparen: SymLParen,
+    pub params: Punctuated<Param, SymComma>,
+    pub rparen: SymRParen,
+    pub ret: Optional<RetType>,
+    pub body: Block,
+}
+
+#[derive(Debug, Clone, SyntaxTree, ToSource)]
+#[TokenSet(FelisToken)]
+pub struct Param {
+    pub id: SyntaxTreeId,
+    pub name: TokenIdent,
+    pub colon: SymColon,
+    pub ty: Type,
+}
+
+#[derive(Debug, Clone, SyntaxTree, ToSource)]
+#[TokenSet(FelisToken)]
+pub struct RetType {
+    pub id: SyntaxTreeId,
+    pub arrow: SymArrow,
+    pub ty: Type,
+}
+
+#[derive(Debug, Clone, SyntaxTree, ToSource)]
+#[TokenSet(FelisToken)]
+pub struct Block {
+    pub id: SyntaxTreeId,
+    pub lbrace: SymLBrace,
+    pub stmts: Rep0<Stmt>,
+    pub tail: Optional<Expr>,
+    pub rbrace: SymRBrace,
+}
+
+#[derive(Debug, Clone, SyntaxTree, ToSource)]
+#[TokenSet(FelisToken)]
+#[recover(SymSemi, stop = SymRBrace)]
+pub enum Stmt {
+    Let(Box<Let>),
+    Return(Return),
+    Assign(Assign),
+    Expr(ExprStmt),
+    BlockExpr(BlockExpr),
+}
+
+#[derive(Debug, Clone, SyntaxTree, ToSource)]
+#[TokenSet(FelisToken)]
+pub struct Let {
+    pub id: SyntaxTreeId,
+    pub kw_let: KwLet,
+    pub name: TokenIdent,
+    pub ty: Optional<TypeAnnotation>,
+    pub eq: SymEq,
+    pub expr: Expr,
+    pub semi: SymSemi,
+}
+
+#[derive(Debug, Clone, SyntaxTree, ToSource)]
+#[TokenSet(FelisToken)]
+pub struct TypeAnnotation {
+    pub id: SyntaxTreeId,
+    pub colon: SymColon,
+    pub ty: Type,
+}
+
+#[derive(Debug, Clone, SyntaxTree, ToSource)]
+#[TokenSet(FelisToken)]
+pub enum Type {
+    Named(TokenIdent),
+}
+
+#[derive(Debug, Clone, SyntaxTree, ToSource)]
+#[TokenSet(FelisToken)]
+pub struct Return {
+    pub id: SyntaxTreeId,
+    pub kw_return: KwReturn,
+    pub expr: Optional<Expr>,
+    pub semi: SymSemi,
+}
+
+#[derive(Debug, Clone, SyntaxTree, ToSource)]
+#[TokenSet(FelisToken)]
+pub struct Assign {
+    pub id: SyntaxTreeId,
+    pub name: TokenIdent,
+    pub eq: SymEq,
+    pub expr: Expr,
+    pub semi: SymSemi,
+}
+
+#[derive(Debug, Clone, SyntaxTree, ToSource)]
+#[TokenSet(FelisToken)]
+pub struct ExprStmt {
+    pub id: SyntaxTreeId,
+    pub expr: Expr,
+    pub semi: SymSemi,
+}
+
+// memoized, since an expression statement and the tail of a block begin alike
+#[derive(Debug, Clone, SyntaxTree, ToSource)]
+#[TokenSet(FelisToken)]
+#[memo]
+pub struct Expr {
+    pub id: SyntaxTreeId,
+    pub infix: Infix<Term, BinOp>,
+}
+
+#[derive(Debug, Clone, SyntaxTree, ToSource)]
+#[TokenSet(FelisToken)]
+pub enum BinOp {
+    Add(SymPlus),
+    Sub(SymMinus),
+    Mul(SymStar),
+    Div(SymSlash),
+    Eq(SymEqEq),
+    Ne(SymNe),
+    Lt(SymLt),
+    Le(SymLe),
+    Gt(SymGt),
+    Ge(SymGe),
+}
+
+impl InfixOperator for BinOp {
+    fn precedence(&self) -> usize {
+        match self {
+            BinOp::Eq(_)
+            | BinOp::Ne(_)
+            | BinOp::Lt(_)
+            | BinOp::Le(_)
+            | BinOp::Gt(_)
+            | BinOp::Ge(_) => 0,
+            BinOp::Add(_) | BinOp::Sub(_) => 1,
+            BinOp::Mul(_) | BinOp::Div(_) => 2,
+        }
+    }
+    fn associativity(&self) -> Associativity {
+        Associativity::Left
+    }
+}
+
+#[derive(Debug, Clone, SyntaxTree, ToSource)]
+#[TokenSet(FelisToken)]
+pub enum Term {
+    Number(TokenNumber),
+    True(KwTrue),
+    False(KwFalse),
+    Call(Call),
+    Var(TokenIdent),
+    Paren(Paren),
+    Break(Box<Break>),
+    Continue(KwContinue),
+    BlockExpr(BlockExpr),
+}
+
+#[derive(Debug, Clone, SyntaxTree, ToSource)]
+#[TokenSet(FelisToken)]
+pub struct Call {
+    pub id: SyntaxTreeId,
+    pub name: TokenIdent,
+    pub lparen: SymLParen,
+    pub args: Punctuated<Expr, SymComma>,
+    pub rparen: SymRParen,
+}
+
+#[derive(Debug, Clone, SyntaxTree, ToSource)]
+#[TokenSet(FelisToken)]
+pub struct Paren {
+    pub id: SyntaxTreeId,
+    pub lparen: SymLParen,
+    pub expr: Box<Expr>,
+    pub rparen: SymRParen,
+}
+
+#[derive(Debug, Clone, SyntaxTree, ToSource)]
+#[TokenSet(FelisToken)]
+pub struct Break {
+    pub id: SyntaxTreeId,
+    pub kw_break: KwBreak,
+    pub expr: Optional<Expr>,
+}
+
+#[derive(Debug, Clone, SyntaxTree, ToSource)]
+#[TokenSet(FelisToken)]
+pub enum BlockExpr {
+    Block(Box<Block>),
+    If(Box<If>),
+    While(Box<While>),
+    Loop(Box<Loop>),
+}
+
+#[derive(Debug, Clone, SyntaxTree, ToSource)]
+#[TokenSet(FelisToken)]
+pub struct If {
+    pub id: SyntaxTreeId,
+    pub kw_if: KwIf,
+    pub cond: Expr,
+    pub then: Block,
+    pub else_: Optional<Else>,
+}
+
+#[derive(Debug, Clone, SyntaxTree, ToSource)]
+#[TokenSet(FelisToken)]
+pub struct Else {
+    pub id: SyntaxTreeId,
+    pub kw_else: KwElse,
+    pub body: ElseBody,
+}
+
+// `else if` needs no braces
+#[derive(Debug, Clone, SyntaxTree, ToSource)]
+#[TokenSet(FelisToken)]
+pub enum ElseBody {
+    If(Box<If>),
+    Block(Box<Block>),
+}
+
+#[derive(Debug, Clone, SyntaxTree, ToSource)]
+#[TokenSet(FelisToken)]
+pub struct While {
+    pub id: SyntaxTreeId,
+    pub kw_while: KwWhile,
+    pub cond: Expr,
+    pub body: Block,
+}
+
+#[derive(Debug, Clone, SyntaxTree, ToSource)]
+#[TokenSet(FelisToken)]
+pub struct Loop {
+    pub id: SyntaxTreeId,
+    pub kw_loop: KwLoop,
+    pub body: Block,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lang::felis::parse, print::ToSource, ProgramFile, ProgramFileId};
+    use std::path::PathBuf;
+
+    fn parse_str(s: &str) -> Result<File, String> {
+        let file = ProgramFile::new(
+            ProgramFileId(1),
+            PathBuf::from("test.felis"),
+            s.chars().collect(),
+        );
+        match parse(&file) {
+            (Some(file), diagnostics) if diagnostics.is_empty() => Ok(file),
+            (_, diagnostics) => Err(diagnostics[0].message.clone()),
+        }
+    }
+
+    #[test]
+    fn test_parse_round_trip() {
+        // printed with the default spacing
+        let s = "fn main () { let x : i32 = 1 + 2 * (3 - x); { x; }; x / 2 } fn f () { }";
+        let file = parse_str(s).unwrap();
+        assert_eq!(file.items.ts.len(), 2);
+        assert_eq!(file.to_source_string(), s);
+
+        let s = "fn f (x : i32, y : bool,) -> i32 { return g (x, f (1, y)); } fn g () { return; }";
+        let file = parse_str(s).unwrap();
+        assert_eq!(file.to_source_string(), s);
+
+        let s = "fn f () { while x < 10 { x = x + 1; if x == 5 { continue; } } \
+                 let y = loop { break 1 + 2 }; if a { } else if b != c { } else { } 0 }";
+        let file = parse_str(s).unwrap();
+        assert_eq!(file.to_source_string(), s);
+    }
+
+    #[test]
+    fn test_parse_error() {
+        assert_eq!(
+            parse_str("fn main() { let x = 1 }").unwrap_err(),
+            "expected one of `!=`, `*`, `+`, `-`, `/`, `;`, `<`, `<=`, `==`, `>`, `>=`"
+        );
+        // tokens are called by their #[name]
+        assert_eq!(
+            parse_str("fn main() { () }").unwrap_err(),
+            "expected one of `(`, `break`, `continue`, `false`, `identifier`, `if`, `loop`, `number`, `true`, `while`, `{`"
+        );
+        assert_eq!(parse_str("fn main() {} let").unwrap_err(), "expected `fn`");
+    }
+
+    #[test]
+    fn test_parse_recovery() {
+        let file = ProgramFile::new(
+            ProgramFileId(1),
+            PathBuf::from("test.felis"),
+            "fn f() { let = 1; x = ; ) ; 1 }\nlet y;\nfn g() { let x = 1 }\nfn h() {}"
+                .chars()
+                .collect(),
+        );
+        let (file, diagnostics) = parse(&file);
+        let errors: Vec<_> = diagnostics
+            .iter()
+            .map(|d| (d.span.begin(), d.message.as_str()))
+            .collect();
+        assert_eq!(
+            errors,
+            vec![
+                (13, "expected `identifier`"),
+                (22, "expected one of `(`, `break`, `continue`, `false`, `identifier`, `if`, `loop`, `number`, `true`, `while`, `{`"),
+                // an error at the first token of a statement is skipped as well
+                (24, "expected one of `(`, `break`, `continue`, `false`, `identifier`, `if`, `let`, `loop`, `number`, `return`, `true`, `while`, `{`"),
+                (32, "expected `fn`"),
+                // the missing `;` breaks the block, and the item is skipped
+                (58, "expected one of `!=`, `*`, `+`, `-`, `/`, `;`, `<`, `<=`, `==`, `>`, `>=`"),
+            ]
+        );
+        let file = file.unwrap();
+        assert_eq!(file.items.ts.len(), 2);
+        assert_eq!(file.items.errors.len(), 2);
+        let Item::Fn(f) = &file.items.ts[0];
+        assert_eq!(f.body.stmts.errors.len(), 3);
+        assert!(f.body.tail.inner.is_some());
+    }
+}
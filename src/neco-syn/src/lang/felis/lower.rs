@@ -0,0 +1,382 @@
+// lowering from the concrete syntax tree to the abstract one
+
+use super::{ast, cst};
+use crate::{Infix, SyntaxTree};
+use neco_table::Id;
+
+pub fn lower(file: &cst::File) -> ast::Ast {
+    let mut lowerer = Lowerer {
+        ast: ast::Ast::new(),
+    };
+    for item in &file.items.ts {
+        let id = lowerer.item(item);
+        lowerer.ast.root.push(id);
+    }
+    lowerer.ast
+}
+
+struct Lowerer {
+    ast: ast::Ast,
+}
+
+impl Lowerer {
+    fn item(&mut self, item: &cst::Item) -> Id<ast::Item> {
+        let kind = match item {
+            cst::Item::Fn(fn_def) => ast::ItemKind::Fn(ast::FnDef {
+                name: ident(&fn_def.name),
+                params: fn_def
+                    .params
+                    .ts
+                    .iter()
+                    .map(|param| ast::Param {
+                        name: ident(&param.name),
+                        ty: ty(&param.ty),
+                        span: param.span(),
+                    })
+                    .collect(),
+                ret: fn_def.ret.inner.as_ref().map(|ret| ty(&ret.ty)),
+                body: self.block(&fn_def.body),
+            }),
+        };
+        self.ast.items.insert(ast::Item {
+            kind,
+            span: item.span(),
+        })
+    }
+    fn block(&mut self, block: &cst::Block) -> ast::Block {
+        let mut stmts: Vec<_> = block.stmts.ts.iter().map(|stmt| self.stmt(stmt)).collect();
+        let mut tail = block.tail.inner.as_ref().map(|expr| self.expr(expr));
+        // `{ if a { 1 } else { 2 } }` has the value of the if
+        if tail.is_none() {
+            if let Some(cst::Stmt::BlockExpr(_)) = block.stmts.ts.last() {
+                match stmts.pop() {
+                    Some(ast::Stmt::Expr(expr)) => tail = Some(expr),
+                    _ => unreachable!(),
+                }
+            }
+        }
+        ast::Block {
+            stmts,
+            tail,
+            span: block.span(),
+        }
+    }
+    fn stmt(&mut self, stmt: &cst::Stmt) -> ast::Stmt {
+        match stmt {
+            cst::Stmt::Let(let_) => ast::Stmt::Let(ast::Let {
+                name: ident(&let_.name),
+                ty: let_.ty.inner.as_ref().map(|annotation| ty(&annotation.ty)),
+                init: self.expr(&let_.expr),
+                span: let_.span(),
+            }),
+            cst::Stmt::Return(return_) => ast::Stmt::Return(ast::Return {
+                value: return_.expr.inner.as_ref().map(|expr| self.expr(expr)),
+                span: return_.span(),
+            }),
+            cst::Stmt::Assign(assign) => ast::Stmt::Assign(ast::Assign {
+                name: ident(&assign.name),
+                value: self.expr(&assign.expr),
+                span: assign.span(),
+            }),
+            cst::Stmt::Expr(expr_stmt) => ast::Stmt::Expr(self.expr(&expr_stmt.expr)),
+            cst::Stmt::BlockExpr(block_expr) => ast::Stmt::Expr(self.block_expr(block_expr)),
+        }
+    }
+    fn expr(&mut self, expr: &cst::Expr) -> Id<ast::Expr> {
+        self.infix(&expr.infix)
+    }
+    fn infix(&mut self, infix: &Infix<cst::Term, cst::BinOp>) -> Id<ast::Expr> {
+        match infix {
+            Infix::Operand(term) => self.term(term),
+            Infix::Binary { lhs, op, rhs, .. } => {
+                let op = match op {
+                    cst::BinOp::Add(_) => ast::BinOp::Add,
+                    cst::BinOp::Sub(_) => ast::BinOp::Sub,
+                    cst::BinOp::Mul(_) => ast::BinOp::Mul,
+                    cst::BinOp::Div(_) => ast::BinOp::Div,
+                    cst::BinOp::Eq(_) => ast::BinOp::Eq,
+                    cst::BinOp::Ne(_) => ast::BinOp::Ne,
+                    cst::BinOp::Lt(_) => ast::BinOp::Lt,
+                    cst::BinOp::Le(_) => ast::BinOp::Le,
+                    cst::BinOp::Gt(_) => ast::BinOp::Gt,
+                    cst::BinOp::Ge(_) => ast::BinOp::Ge,
+                };
+                let lhs = self.infix(lhs);
+                let rhs = self.infix(rhs);
+                self.ast.exprs.insert(ast::Expr {
+                    kind: ast::ExprKind::Binary { op, lhs, rhs },
+                    span: infix.span(),
+                })
+            }
+        }
+    }
+    fn term(&mut self, term: &cst::Term) -> Id<ast::Expr> {
+        let kind = match term {
+            cst::Term::Number(number) => {
+                ast::ExprKind::Literal(ast::Literal::Int(number.number.replace('_', "")))
+            }
+            cst::Term::True(_) => ast::ExprKind::Literal(ast::Literal::Bool(true)),
+            cst::Term::False(_) => ast::ExprKind::Literal(ast::Literal::Bool(false)),
+            cst::Term::Call(call) => ast::ExprKind::Call {
+                callee: ident(&call.name),
+                args: call.args.ts.iter().map(|arg| self.expr(arg)).collect(),
+            },
+            cst::Term::Var(var) => ast::ExprKind::Var(var.ident.clone()),
+            // parentheses leave no node
+            cst::Term::Paren(paren) => return self.expr(&paren.expr),
+            cst::Term::Break(break_) => {
+                ast::ExprKind::Break(break_.expr.inner.as_ref().map(|expr| self.expr(expr)))
+            }
+            cst::Term::Continue(_) => ast::ExprKind::Continue,
+            cst::Term::BlockExpr(block_expr) => return self.block_expr(block_expr),
+        };
+        self.ast.exprs.insert(ast::Expr {
+            kind,
+            span: term.span(),
+        })
+    }
+    fn block_expr(&mut self, block_expr: &cst::BlockExpr) -> Id<ast::Expr> {
+        let kind = match block_expr {
+            cst::BlockExpr::Block(block) => ast::ExprKind::Block(self.block(block)),
+            cst::BlockExpr::If(if_) => return self.if_(if_),
+            cst::BlockExpr::While(while_) => ast::ExprKind::While {
+                cond: self.expr(&while_.cond),
+                body: self.block(&while_.body),
+            },
+            cst::BlockExpr::Loop(loop_) => ast::ExprKind::Loop(self.block(&loop_.body)),
+        };
+        self.ast.exprs.insert(ast::Expr {
+            kind,
+            span: block_expr.span(),
+        })
+    }
+    fn if_(&mut self, if_: &cst::If) -> Id<ast::Expr> {
+        let cond = self.expr(&if_.cond);
+        let then = self.block(&if_.then);
+        let else_ = if_.else_.inner.as_ref().map(|else_| match &else_.body {
+            cst::ElseBody::If(if_) => self.if_(if_),
+            cst::ElseBody::Block(block) => {
+                let block = self.block(block);
+                let span = block.span;
+                self.ast.exprs.insert(ast::Expr {
+                    kind: ast::ExprKind::Block(block),
+                    span,
+                })
+            }
+        });
+        self.ast.exprs.insert(ast::Expr {
+            kind: ast::ExprKind::If { cond, then, else_ },
+            span: if_.span(),
+        })
+    }
+}
+
+fn ident(t: &super::TokenIdent) -> ast::Ident {
+    ast::Ident {
+        name: t.ident.clone(),
+        span: t.span,
+    }
+}
+
+fn ty(t: &cst::Type) -> ast::TypeExpr {
+    match t {
+        cst::Type::Named(name) => ast::TypeExpr {
+            kind: ast::TypeExprKind::Named(name.ident.clone()),
+            span: name.span,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lang::felis::parse, Project};
+    use std::path::PathBuf;
+
+    fn lower_str(project: &mut Project, s: &str) -> ast::Ast {
+        let file_id = project.add_file(PathBuf::from("test.felis"), s.chars().collect());
+        let file = parse(project.get_file(file_id).unwrap()).0.unwrap();
+        lower(&file)
+    }
+
+    #[test]
+    fn test_lower() {
+        let mut project = Project::new();
+        let ast = lower_str(
+            &mut project,
+            "fn main() {\n    let x: i32 = 1_000 + (2 * x);\n    x;\n    true\n}\n",
+        );
+        assert_eq!(ast.root.len(), 1);
+        let item = ast.item(ast.root[0]);
+        assert_eq!(project.snippet(item.span).unwrap().lines().count(), 5);
+        let body = match &item.kind {
+            ast::ItemKind::Fn(fn_def) => {
+                assert_eq!(fn_def.name.name, "main");
+                &fn_def.body
+            }
+        };
+        assert_eq!(body.stmts.len(), 2);
+
+        let let_ = match &body.stmts[0] {
+            ast::Stmt::Let(let_) => let_,
+            _ => panic!(),
+        };
+        assert_eq!(let_.name.name, "x");
+        assert_eq!(
+            let_.ty.as_ref().unwrap().kind,
+            ast::TypeExprKind::Named("i32".to_string())
+        );
+        assert_eq!(
+            project.snippet(let_.span).unwrap(),
+            "let x: i32 = 1_000 + (2 * x);"
+        );
+        let init = ast.expr(let_.init);
+        assert_eq!(project.snippet(init.span).unwrap(), "1_000 + (2 * x)");
+        let (lhs, rhs) = match &init.kind {
+            ast::ExprKind::Binary {
+                op: ast::BinOp::Add,
+                lhs,
+                rhs,
+            } => (ast.expr(*lhs), ast.expr(*rhs)),
+            _ => panic!(),
+        };
+        assert_eq!(
+            lhs.kind,
+            ast::ExprKind::Literal(ast::Literal::Int("1000".to_string()))
+        );
+        // the parentheses are not a node
+        assert_eq!(project.snippet(rhs.span).unwrap(), "2 * x");
+
+        let tail = ast.expr(body.tail.unwrap());
+        assert_eq!(tail.kind, ast::ExprKind::Literal(ast::Literal::Bool(true)));
+    }
+
+    #[test]
+    fn test_lower_fn() {
+        let mut project = Project::new();
+        let ast = lower_str(
+            &mut project,
+            "fn add(x: i32, y: i32) -> i32 { return x + y; }\nfn main() { add(1, add(2, 3)); }",
+        );
+        let fn_defs: Vec<_> = ast
+            .root
+            .iter()
+            .map(|id| match &ast.item(*id).kind {
+                ast::ItemKind::Fn(fn_def) => fn_def,
+            })
+            .collect();
+        let add = fn_defs[0];
+        assert_eq!(add.params.len(), 2);
+        assert_eq!(add.params[1].name.name, "y");
+        assert_eq!(project.snippet(add.params[1].span).unwrap(), "y: i32");
+        assert_eq!(
+            add.ret.as_ref().unwrap().kind,
+            ast::TypeExprKind::Named("i32".to_string())
+        );
+        match &add.body.stmts[0] {
+            ast::Stmt::Return(return_) => {
+                assert_eq!(project.snippet(return_.span).unwrap(), "return x + y;");
+                assert!(return_.value.is_some());
+            }
+            _ => panic!(),
+        }
+
+        let main = fn_defs[1];
+        assert!(main.params.is_empty() && main.ret.is_none());
+        let call = match &main.body.stmts[0] {
+            ast::Stmt::Expr(id) => ast.expr(*id),
+            _ => panic!(),
+        };
+        assert_eq!(project.snippet(call.span).unwrap(), "add(1, add(2, 3))");
+        match &call.kind {
+            ast::ExprKind::Call { callee, args } => {
+                assert_eq!(callee.name, "add");
+                assert_eq!(args.len(), 2);
+                assert_eq!(
+                    project.snippet(ast.expr(args[1]).span).unwrap(),
+                    "add(2, 3)"
+                );
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_lower_control_flow() {
+        let mut project = Project::new();
+        let ast = lower_str(
+            &mut project,
+            "fn f() {\n    while x < 1 { x = x + 1; }\n    if a { 1 } else if b { break 2 } else { continue }\n}",
+        );
+        let body = match &ast.item(ast.root[0]).kind {
+            ast::ItemKind::Fn(fn_def) => &fn_def.body,
+        };
+        // the last if is the value of the block
+        assert_eq!(body.stmts.len(), 1);
+        let while_ = match &body.stmts[0] {
+            ast::Stmt::Expr(id) => ast.expr(*id),
+            _ => panic!(),
+        };
+        match &while_.kind {
+            ast::ExprKind::While { cond, body } => {
+                assert_eq!(project.snippet(ast.expr(*cond).span).unwrap(), "x < 1");
+                match &body.stmts[0] {
+                    ast::Stmt::Assign(assign) => {
+                        assert_eq!(assign.name.name, "x");
+                        assert_eq!(project.snippet(assign.span).unwrap(), "x = x + 1;");
+                    }
+                    _ => panic!(),
+                }
+            }
+            _ => panic!(),
+        }
+
+        let if_ = ast.expr(body.tail.unwrap());
+        let else_ = match &if_.kind {
+            ast::ExprKind::If { then, else_, .. } => {
+                assert!(then.tail.is_some());
+                ast.expr(else_.unwrap())
+            }
+            _ => panic!(),
+        };
+        assert_eq!(
+            project.snippet(else_.span).unwrap(),
+            "if b { break 2 } else { continue }"
+        );
+        match &else_.kind {
+            ast::ExprKind::If { then, else_, .. } => {
+                let break_ = ast.expr(then.tail.unwrap());
+                assert!(matches!(break_.kind, ast::ExprKind::Break(Some(_))));
+                let else_ = ast.expr(else_.unwrap());
+                match &else_.kind {
+                    ast::ExprKind::Block(block) => {
+                        assert_eq!(ast.expr(block.tail.unwrap()).kind, ast::ExprKind::Continue)
+                    }
+                    _ => panic!(),
+                }
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_lower_precedence() {
+        let mut project = Project::new();
+        let ast = lower_str(&mut project, "fn f() { 1 - 2 - 3 * 4 }");
+        let body = match &ast.item(ast.root[0]).kind {
+            ast::ItemKind::Fn(fn_def) => &fn_def.body,
+        };
+        let root = ast.expr(body.tail.unwrap());
+        match &root.kind {
+            ast::ExprKind::Binary {
+                op: ast::BinOp::Sub,
+                lhs,
+                rhs,
+            } => {
+                assert_eq!(project.snippet(ast.expr(*lhs).span).unwrap(), "1 - 2");
+                assert_eq!(project.snippet(ast.expr(*rhs).span).unwrap(), "3 * 4");
+            }
+            _ => panic!(),
+        }
+    }
+}
@@ -0,0 +1,265 @@
+use crate::{diagnostic::Diagnostic, ProgramFile, Span, SyntaxTreeId, SyntaxTreeIdManager, Token};
+use std::collections::BTreeSet;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClassItem {
+    Range(char, char),
+    // \s
+    Whitespace,
+    // \d
+    Digit,
+    // \w
+    Word,
+}
+
+impl ClassItem {
+    fn contains(&self, c: char) -> bool {
+        match self {
+            ClassItem::Range(begin, end) => *begin <= c && c <= *end,
+            ClassItem::Whitespace => c.is_whitespace(),
+            ClassItem::Digit => c.is_ascii_digit(),
+            ClassItem::Word => c.is_ascii_alphanumeric() || c == '_',
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Pattern {
+    Literal(String),
+    Char(char),
+    Any,
+    Class {
+        negated: bool,
+        items: Vec<ClassItem>,
+    },
+    Seq(Vec<Pattern>),
+    Alt(Vec<Pattern>),
+    Repeat {
+        pattern: Box<Pattern>,
+        min: usize,
+        max: Option<usize>,
+    },
+}
+
+impl Pattern {
+    // the end positions of all matches of self starting at i
+    fn ends(&self, cs: &[char], i: usize) -> BTreeSet<usize> {
+        let mut res = BTreeSet::new();
+        match self {
+            Pattern::Literal(s) => {
+                let mut j = i;
+                for c in s.chars() {
+                    if cs.get(j) != Some(&c) {
+                        return res;
+                    }
+                    j += 1;
+                }
+                res.insert(j);
+            }
+            Pattern::Char(c) => {
+                if cs.get(i) == Some(c) {
+                    res.insert(i + 1);
+                }
+            }
+            Pattern::Any => {
+                if i < cs.len() {
+                    res.insert(i + 1);
+                }
+            }
+            Pattern::Class { negated, items } => {
+                if let Some(c) = cs.get(i) {
+                    if items.iter().any(|item| item.contains(*c)) != *negated {
+                        res.insert(i + 1);
+                    }
+                }
+            }
+            Pattern::Seq(patterns) => {
+                res.insert(i);
+                for pattern in patterns {
+                    res = res.into_iter().flat_map(|j| pattern.ends(cs, j)).collect();
+                }
+            }
+            Pattern::Alt(patterns) => {
+                for pattern in patterns {
+                    res.extend(pattern.ends(cs, i));
+                }
+            }
+            Pattern::Repeat { pattern, min, max } => {
+                let mut current: BTreeSet<usize> = std::iter::once(i).collect();
+                let mut count = 0;
+                loop {
+                    if count >= *min {
+                        res.extend(current.iter().copied());
+                    }
+                    if max.is_some_and(|max| count >= max) {
+                        break;
+                    }
+                    let next: BTreeSet<usize> =
+                        current.iter().flat_map(|j| pattern.ends(cs, *j)).collect();
+                    // stop once no new position can be reached
+                    if next.is_empty() || (count >= *min && next.is_subset(&res)) {
+                        break;
+                    }
+                    current = next;
+                    count += 1;
+                }
+            }
+        }
+        res
+    }
+    // the end of the longest non-empty match starting at i
+    pub fn longest_match(&self, cs: &[char], i: usize) -> Option<usize> {
+        self.ends(cs, i)
+            .into_iter()
+            .next_back()
+            .filter(|end| *end > i)
+    }
+}
+
+pub trait FromText {
+    fn from_text(text: &str) -> Self;
+}
+
+impl FromText for String {
+    fn from_text(text: &str) -> Self {
+        text.to_string()
+    }
+}
+
+impl FromText for char {
+    fn from_text(text: &str) -> Self {
+        text.chars().next().unwrap()
+    }
+}
+
+// implemented by #[derive(Token)] when the token has only `id`, `span` and a #[text] field
+pub trait FromLexeme: Token {
+    fn from_lexeme(id: SyntaxTreeId, span: Span, text: &str) -> Self;
+}
+
+pub trait Lexer: Sized {
+    fn patterns() -> Vec<Pattern>;
+    // the token for a match of patterns()[index]
+    fn token(index: usize, id: SyntaxTreeId, span: Span, text: &str) -> Self;
+    // maximal munch; the earlier pattern wins a tie.
+    // characters no pattern matches are skipped with a diagnostic, which has no code.
+    fn lex(
+        program_file: &ProgramFile,
+        ids: &mut SyntaxTreeIdManager,
+    ) -> (Vec<Self>, Vec<Diagnostic>) {
+        let (tokens, diagnostics, _) = Self::lex_from(program_file, ids, 0, |_| false);
+        (tokens, diagnostics)
+    }
+    // lex from the offset begin until stop returns true for the offset lexing reached.
+    // returns the offset where lexing stopped as well.
+    fn lex_from<F: FnMut(usize) -> bool>(
+        program_file: &ProgramFile,
+        ids: &mut SyntaxTreeIdManager,
+        begin: usize,
+        mut stop: F,
+    ) -> (Vec<Self>, Vec<Diagnostic>, usize) {
+        let program_file_id = program_file.program_file_id();
+        let cs = program_file.body();
+        let patterns = Self::patterns();
+        let mut tokens = vec![];
+        let mut diagnostics = vec![];
+        let mut i = begin;
+        while i < cs.len() {
+            let mut longest: Option<(usize, usize)> = None;
+            for (index, pattern) in patterns.iter().enumerate() {
+                if let Some(end) = pattern.longest_match(cs, i) {
+                    if longest.is_none_or(|(longest_end, _)| end > longest_end) {
+                        longest = Some((end, index));
+                    }
+                }
+            }
+            match longest {
+                Some((end, index)) => {
+                    let text: String = cs[i..end].iter().collect();
+                    let span = Span::new_with_span(program_file_id, i, end);
+                    tokens.push(Self::token(index, ids.create(), span, &text));
+                    i = end;
+                }
+                None => {
+                    let span = Span::new_with_span(program_file_id, i, i + 1);
+                    diagnostics.push(Diagnostic::error(
+                        format!("unknown character `{}`", cs[i]),
+                        span,
+                    ));
+                    i += 1;
+                }
+            }
+            if stop(i) {
+                break;
+            }
+        }
+        (tokens, diagnostics, i)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chars(s: &str) -> Vec<char> {
+        s.chars().collect()
+    }
+
+    fn star(pattern: Pattern) -> Pattern {
+        Pattern::Repeat {
+            pattern: Box::new(pattern),
+            min: 0,
+            max: None,
+        }
+    }
+
+    #[test]
+    fn test_longest_match() {
+        let ident = Pattern::Seq(vec![
+            Pattern::Class {
+                negated: false,
+                items: vec![ClassItem::Range('a', 'z'), ClassItem::Range('_', '_')],
+            },
+            star(Pattern::Class {
+                negated: false,
+                items: vec![ClassItem::Word],
+            }),
+        ]);
+        assert_eq!(ident.longest_match(&chars("ab_1 c"), 0), Some(4));
+        assert_eq!(ident.longest_match(&chars("ab_1 c"), 5), Some(6));
+        assert_eq!(ident.longest_match(&chars("1ab"), 0), None);
+
+        let keyword = Pattern::Alt(vec![
+            Pattern::Literal("if".to_string()),
+            Pattern::Literal("iff".to_string()),
+        ]);
+        assert_eq!(keyword.longest_match(&chars("iff"), 0), Some(3));
+        assert_eq!(keyword.longest_match(&chars("ifx"), 0), Some(2));
+
+        // a*a matches all of aaa
+        let a = Pattern::Seq(vec![star(Pattern::Char('a')), Pattern::Char('a')]);
+        assert_eq!(a.longest_match(&chars("aaab"), 0), Some(3));
+
+        // empty matches are not tokens
+        assert_eq!(star(Pattern::Char('a')).longest_match(&chars("b"), 0), None);
+
+        let not_newline = Pattern::Class {
+            negated: true,
+            items: vec![ClassItem::Range('\n', '\n')],
+        };
+        let comment = Pattern::Seq(vec![Pattern::Literal("//".to_string()), star(not_newline)]);
+        assert_eq!(comment.longest_match(&chars("// x\ny"), 0), Some(4));
+    }
+
+    #[test]
+    fn test_repeat_bounds() {
+        let ab = Pattern::Repeat {
+            pattern: Box::new(Pattern::Literal("ab".to_string())),
+            min: 2,
+            max: Some(3),
+        };
+        assert_eq!(ab.longest_match(&chars("ab"), 0), None);
+        assert_eq!(ab.longest_match(&chars("abab"), 0), Some(4));
+        assert_eq!(ab.longest_match(&chars("abababab"), 0), Some(6));
+    }
+}
@@ -1,31 +1,200 @@
-#![feature(once_cell)]
+extern crate self as neco_syn;
 
+pub mod diagnostic;
+pub mod incremental;
 pub mod lang;
+pub mod lexer;
+pub mod print;
+pub mod visit;
 
-use std::{lazy::OnceCell, path::PathBuf};
+use neco_table::{Id, IdManager};
+use std::{
+    any::{Any, TypeId},
+    cell::Cell,
+    collections::{BTreeSet, HashMap},
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
 
 #[derive(Debug, Clone)]
 pub struct Project {
     files: Vec<ProgramFile>,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct ProgramFileId(usize);
+impl Project {
+    pub fn new() -> Project {
+        Project { files: vec![] }
+    }
+    // ProgramFileId(0) is left for dummy spans
+    pub fn add_file(&mut self, path: PathBuf, body: Vec<char>) -> ProgramFileId {
+        let program_file_id = ProgramFileId(self.files.len() + 1);
+        self.files
+            .push(ProgramFile::new(program_file_id, path, body));
+        program_file_id
+    }
+    pub fn load_file<P: AsRef<Path>>(&mut self, path: P) -> io::Result<ProgramFileId> {
+        let path = path.as_ref();
+        let body = fs::read_to_string(path)?;
+        Ok(self.add_file(path.to_path_buf(), body.chars().collect()))
+    }
+    pub fn load_files<P: AsRef<Path>>(&mut self, paths: &[P]) -> io::Result<Vec<ProgramFileId>> {
+        let mut res = vec![];
+        for path in paths {
+            res.push(self.load_file(path)?);
+        }
+        Ok(res)
+    }
+    // load all .felis files under dir, in the order of their paths
+    pub fn load_dir<P: AsRef<Path>>(&mut self, dir: P) -> io::Result<Vec<ProgramFileId>> {
+        let mut paths = vec![];
+        collect_felis_files(dir.as_ref(), &mut paths)?;
+        paths.sort();
+        self.load_files(&paths)
+    }
+    pub fn get_file(&self, program_file_id: ProgramFileId) -> Option<&ProgramFile> {
+        self.files.get(program_file_id.0.checked_sub(1)?)
+    }
+    pub fn get_file_by_path<P: AsRef<Path>>(&self, path: P) -> Option<&ProgramFile> {
+        let path = path.as_ref();
+        self.files.iter().find(|file| file.path == path)
+    }
+    pub fn files(&self) -> &[ProgramFile] {
+        &self.files
+    }
+    pub fn location(&self, span: Span) -> Option<Location> {
+        let file = self.get_file(span.program_file_id)?;
+        Some(Location {
+            path: file.path.clone(),
+            begin: file.line_column(span.begin),
+            end: file.line_column(span.end),
+        })
+    }
+    pub fn snippet(&self, span: Span) -> Option<String> {
+        let file = self.get_file(span.program_file_id)?;
+        file.text(span.begin, span.end)
+    }
+}
 
-const NEXT_PROGRAM_FILE_ID: OnceCell<ProgramFileId> = OnceCell::new();
-pub fn gen_next_program_file_id() -> ProgramFileId {
-    let res = NEXT_PROGRAM_FILE_ID
-        .get_or_init(|| ProgramFileId(1))
-        .clone();
-    NEXT_PROGRAM_FILE_ID.set(ProgramFileId(res.0 + 1)).unwrap();
-    res.clone()
+fn collect_felis_files(dir: &Path, paths: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_felis_files(&path, paths)?;
+        } else if path.extension().is_some_and(|ext| ext == "felis") {
+            paths.push(path);
+        }
+    }
+    Ok(())
 }
 
+impl Default for Project {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ProgramFileId(usize);
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct ProgramFile {
     program_file_id: ProgramFileId,
     path: PathBuf,
     body: Vec<char>,
+    // offsets of the first char of each line
+    line_starts: Vec<usize>,
+}
+
+impl ProgramFile {
+    pub(crate) fn new(
+        program_file_id: ProgramFileId,
+        path: PathBuf,
+        body: Vec<char>,
+    ) -> ProgramFile {
+        ProgramFile {
+            program_file_id,
+            path,
+            line_starts: line_starts(&body),
+            body,
+        }
+    }
+    pub fn program_file_id(&self) -> ProgramFileId {
+        self.program_file_id
+    }
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    pub fn body(&self) -> &[char] {
+        &self.body
+    }
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+    // offset can be body.len(), which is the position just after the last char
+    pub fn line_column(&self, offset: usize) -> LineColumn {
+        let line = match self.line_starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(line) => line - 1,
+        };
+        LineColumn {
+            line: line + 1,
+            column: offset - self.line_starts[line] + 1,
+        }
+    }
+    // the chars of the line (1-origin), without the line break
+    pub fn line(&self, line: usize) -> Option<&[char]> {
+        let begin = *self.line_starts.get(line.checked_sub(1)?)?;
+        let mut end = match self.line_starts.get(line) {
+            Some(next) => next - 1,
+            None => self.body.len(),
+        };
+        if end > begin && self.body[end - 1] == '\r' {
+            end -= 1;
+        }
+        Some(&self.body[begin..end])
+    }
+    pub fn text(&self, begin: usize, end: usize) -> Option<String> {
+        if begin > end || end > self.body.len() {
+            return None;
+        }
+        Some(self.body[begin..end].iter().collect())
+    }
+}
+
+fn line_starts(body: &[char]) -> Vec<usize> {
+    let mut res = vec![0];
+    for (i, c) in body.iter().enumerate() {
+        if *c == '\n' {
+            res.push(i + 1);
+        }
+    }
+    res
+}
+
+// 1-origin, and the column is counted in chars
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LineColumn {
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Location {
+    pub path: PathBuf,
+    pub begin: LineColumn,
+    pub end: LineColumn,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}",
+            self.path.display(),
+            self.begin.line,
+            self.begin.column
+        )
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -35,6 +204,12 @@ pub struct Span {
     end: usize,
 }
 
+impl Default for Span {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Span {
     pub fn new() -> Span {
         Span {
@@ -50,10 +225,55 @@ impl Span {
             end,
         }
     }
+    pub fn program_file_id(&self) -> ProgramFileId {
+        self.program_file_id
+    }
+    pub fn is_empty(&self) -> bool {
+        self.begin == self.end
+    }
+    // from the first non-empty span to the last one.
+    // if all are empty, the first span tells where the empty tree is.
+    pub fn cover(spans: &[Span]) -> Span {
+        let mut non_empty = spans.iter().filter(|span| !span.is_empty());
+        match (non_empty.next(), non_empty.next_back()) {
+            (Some(first), Some(last)) => {
+                Span::new_with_span(first.program_file_id, first.begin, last.end)
+            }
+            (Some(first), None) => *first,
+            _ => spans.first().copied().unwrap_or_default(),
+        }
+    }
+    pub fn begin(&self) -> usize {
+        self.begin
+    }
+    pub fn end(&self) -> usize {
+        self.end
+    }
+    // moved by delta chars, for trees after an edit
+    pub fn shift(self, delta: isize) -> Span {
+        Span {
+            program_file_id: self.program_file_id,
+            begin: self
+                .begin
+                .checked_add_signed(delta)
+                .expect("span shifted before the start of the file"),
+            end: self
+                .end
+                .checked_add_signed(delta)
+                .expect("span shifted before the start of the file"),
+        }
+    }
 }
 
 pub trait Token: Clone {
+    // how "expected ..." messages call the token, like "identifier"
+    const NAME: &'static str;
     fn span(&self) -> Span;
+    fn shift(&mut self, delta: isize);
+    // the text compared by `#[token("...")]`
+    fn text(&self) -> Option<String> {
+        None
+    }
 }
 
 pub trait TokenSetMatch<Set: ?Sized>: Sized {
@@ -61,19 +281,244 @@ pub trait TokenSetMatch<Set: ?Sized>: Sized {
 }
 
 pub trait TokenSet {
+    fn span(&self) -> Span;
+    fn id(&self) -> SyntaxTreeId;
+    fn shift(&mut self, delta: isize);
+    // trivia such as spaces and comments are not seen by parsers
+    fn is_trivia(&self) -> bool {
+        false
+    }
     fn token_match<U: TokenSetMatch<Self>>(&self) -> Option<U> {
         U::token_match(self)
     }
 }
 
+// a token of type Base whose text is VALUE, like a keyword or a symbol
+pub trait TokenValue: Sized {
+    type Base: Token;
+    const VALUE: &'static str;
+    fn from_base(base: Self::Base) -> Self;
+    fn base(&self) -> &Self::Base;
+}
+
+// the trivia around a token.
+// trivia before a token is its leading trivia, and trivia after the last token is the trailing trivia of it.
+#[derive(Debug, Clone)]
+pub struct Trivia<T> {
+    pub leading: Vec<T>,
+    pub trailing: Vec<T>,
+}
+
 pub struct Tokens<T: TokenSet> {
     ts: Vec<T>,
+    trivia: Vec<Trivia<T>>,
+    // the trivia of a file without any other token
+    end_trivia: Vec<T>,
+    indices: HashMap<SyntaxTreeId, usize>,
     i: usize,
+    ids: SyntaxTreeIdManager,
+    recovery: bool,
+    errors: Vec<ParseError>,
+    furthest: Option<ParseError>,
+    // one past the furthest token looked at, which tells what a memoized result depends on
+    reach: Cell<usize>,
+    memo: HashMap<(usize, TypeId), Box<dyn Memo<T>>>,
+    // memoized parses in progress, and whether left recursion was detected in each
+    heads: HashMap<(usize, TypeId), bool>,
+}
+
+struct MemoEntry<P> {
+    res: ParserResult<P>,
+    end: usize,
+    reach: usize,
+    errors: Vec<ParseError>,
+}
+
+trait Memo<T: TokenSet> {
+    fn as_any(&self) -> &dyn Any;
+    fn reach(&self) -> usize;
+    fn shift(&mut self, token_delta: isize, delta: isize);
+}
+
+impl<T: TokenSet, P: SyntaxTree<T> + 'static> Memo<T> for MemoEntry<P> {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn reach(&self) -> usize {
+        self.reach
+    }
+    fn shift(&mut self, token_delta: isize, delta: isize) {
+        match &mut self.res {
+            ParserResult::Ok(t) => t.shift(delta),
+            ParserResult::Fail(e) | ParserResult::Err(e) => e.shift(token_delta, delta),
+        }
+        self.end = self
+            .end
+            .checked_add_signed(token_delta)
+            .expect("memo entry shifted before the first token");
+        self.reach = self
+            .reach
+            .checked_add_signed(token_delta)
+            .expect("memo entry shifted before the first token");
+        for e in &mut self.errors {
+            e.shift(token_delta, delta);
+        }
+    }
 }
 
 impl<T: TokenSet> Tokens<T> {
-    pub fn new(tokens: Vec<T>) -> Tokens<T> {
-        Tokens { ts: tokens, i: 0 }
+    // ids should be the manager which gave the ids to tokens, so that the ids of trees don't collide with them
+    pub fn new(tokens: Vec<T>, ids: SyntaxTreeIdManager) -> Tokens<T> {
+        let mut res = Tokens {
+            ts: vec![],
+            trivia: vec![],
+            end_trivia: vec![],
+            indices: HashMap::new(),
+            i: 0,
+            ids,
+            recovery: false,
+            errors: vec![],
+            furthest: None,
+            reach: Cell::new(0),
+            memo: HashMap::new(),
+            heads: HashMap::new(),
+        };
+        res.set_tokens(tokens);
+        res
+    }
+    // replace all tokens, and split off the trivia
+    fn set_tokens(&mut self, tokens: Vec<T>) {
+        let mut ts = vec![];
+        let mut trivia: Vec<Trivia<T>> = vec![];
+        let mut leading = vec![];
+        for t in tokens {
+            if t.is_trivia() {
+                leading.push(t);
+            } else {
+                ts.push(t);
+                trivia.push(Trivia {
+                    leading: std::mem::take(&mut leading),
+                    trailing: vec![],
+                });
+            }
+        }
+        let end_trivia = match trivia.last_mut() {
+            Some(last) => {
+                last.trailing = leading;
+                vec![]
+            }
+            None => leading,
+        };
+        self.indices = ts.iter().enumerate().map(|(i, t)| (t.id(), i)).collect();
+        self.ts = ts;
+        self.trivia = trivia;
+        self.end_trivia = end_trivia;
+    }
+    pub fn trivia(&self, i: usize) -> Option<&Trivia<T>> {
+        self.trivia.get(i)
+    }
+    pub fn trivia_of(&self, id: SyntaxTreeId) -> Option<&Trivia<T>> {
+        self.trivia(*self.indices.get(&id)?)
+    }
+    // all tokens including trivia, in the order of the source
+    pub fn lossless_tokens(&self) -> Vec<&T> {
+        let mut res = vec![];
+        for (t, trivia) in self.ts.iter().zip(&self.trivia) {
+            res.extend(&trivia.leading);
+            res.push(t);
+            res.extend(&trivia.trailing);
+        }
+        res.extend(&self.end_trivia);
+        res
+    }
+    fn take_lossless_tokens(&mut self) -> Vec<T> {
+        let mut res = vec![];
+        let trivia = std::mem::take(&mut self.trivia);
+        for (t, trivia) in std::mem::take(&mut self.ts).into_iter().zip(trivia) {
+            res.extend(trivia.leading);
+            res.push(t);
+            res.extend(trivia.trailing);
+        }
+        res.append(&mut self.end_trivia);
+        res
+    }
+    // the source the tokens were lexed from, when they cover the whole file
+    pub fn source_text(&self, program_file: &ProgramFile) -> String {
+        self.lossless_tokens()
+            .into_iter()
+            .filter_map(|t| program_file.text(t.span().begin(), t.span().end()))
+            .collect()
+    }
+    pub fn recovery(&self) -> bool {
+        self.recovery
+    }
+    pub fn set_recovery(&mut self, recovery: bool) {
+        self.recovery = recovery;
+    }
+    pub fn errors(&self) -> &[ParseError] {
+        &self.errors
+    }
+    pub fn push_error(&mut self, e: ParseError) {
+        self.errors.push(e);
+    }
+    // skip tokens until P is parsed. P is consumed on success, and nothing is consumed otherwise.
+    pub fn skip_until<P: SyntaxTree<T>>(&mut self) -> bool {
+        self.skip(|tokens| tokens.lookahead::<P>().then_some(true))
+    }
+    // skip_until, but give up at a Stop, which is left to an outer parser
+    pub fn skip_until_stop<P: SyntaxTree<T>, Stop: SyntaxTree<T>>(&mut self) -> bool {
+        self.skip(|tokens| {
+            let i = tokens.i;
+            if tokens.lookahead::<Stop>() {
+                return Some(false);
+            }
+            tokens.i = i;
+            tokens.lookahead::<P>().then_some(true)
+        })
+    }
+    // skip at least one token, and then until P begins or the input ends. P is not consumed.
+    pub fn skip_to<P: SyntaxTree<T>>(&mut self) -> bool {
+        if self.is_eof() {
+            return false;
+        }
+        self.i += 1;
+        let found = self.skip(|tokens| {
+            let i = tokens.i;
+            let found = tokens.lookahead::<P>();
+            tokens.i = i;
+            found.then_some(true)
+        });
+        if !found {
+            self.i = self.ts.len();
+        }
+        true
+    }
+    // step through the tokens until f decides. on false or at the end of input nothing is consumed.
+    fn skip<F: FnMut(&mut Tokens<T>) -> Option<bool>>(&mut self, mut f: F) -> bool {
+        let initial_i = self.i;
+        while self.i < self.ts.len() {
+            let i = self.i;
+            match f(self) {
+                Some(true) => return true,
+                Some(false) => break,
+                None => self.i = i + 1,
+            }
+        }
+        self.i = initial_i;
+        false
+    }
+    // whether P is parsed here, which consumes it. the failures don't count as the furthest ones.
+    fn lookahead<P: SyntaxTree<T>>(&mut self) -> bool {
+        let furthest = self.furthest.clone();
+        let ok = self.parse::<P>().is_ok();
+        self.furthest = furthest;
+        ok
+    }
+    pub fn gen_id(&mut self) -> SyntaxTreeId {
+        self.ids.create()
+    }
+    pub fn into_ids(self) -> SyntaxTreeIdManager {
+        self.ids
     }
     pub fn get_i(&self) -> usize {
         self.i
@@ -81,32 +526,221 @@ impl<T: TokenSet> Tokens<T> {
     pub fn set_i(&mut self, i: usize) {
         self.i = i;
     }
-    pub fn get_token(&self) -> &T {
-        self.ts.get(self.i).unwrap()
+    // the span of the tokens from begin_i to the current position.
+    // if nothing was consumed, it is the empty span at the beginning of the next token.
+    pub fn span_from(&self, begin_i: usize) -> Span {
+        self.look(begin_i);
+        if begin_i < self.i {
+            let begin = self.ts[begin_i].span();
+            let end = self.ts[self.i - 1].span();
+            return Span::new_with_span(begin.program_file_id, begin.begin, end.end);
+        }
+        match (self.ts.get(begin_i), begin_i.checked_sub(1)) {
+            (Some(t), _) => {
+                let span = t.span();
+                Span::new_with_span(span.program_file_id, span.begin, span.begin)
+            }
+            (None, Some(prev)) => match self.ts.get(prev) {
+                Some(t) => {
+                    let span = t.span();
+                    Span::new_with_span(span.program_file_id, span.end, span.end)
+                }
+                None => Span::new(),
+            },
+            (None, None) => Span::new(),
+        }
+    }
+    // None at the end of input
+    pub fn get_token(&self) -> Option<&T> {
+        self.look(self.i);
+        self.ts.get(self.i)
+    }
+    pub fn is_eof(&self) -> bool {
+        self.look(self.i);
+        self.i >= self.ts.len()
+    }
+    fn look(&self, i: usize) {
+        if self.reach.get() <= i {
+            self.reach.set(i + 1);
+        }
     }
     pub fn next(&mut self) {
         self.i += 1;
     }
+    // the errors recovered from in a failed parse are dropped, since another alternative is tried
     pub fn parse<P: SyntaxTree<T>>(&mut self) -> ParserResult<P> {
-        P::parse(self)
+        let errors_len = self.errors.len();
+        let res = P::parse(self);
+        if let ParserResult::Fail(e) = &res {
+            self.errors.truncate(errors_len);
+            self.furthest = Some(match self.furthest.take() {
+                Some(furthest) => furthest.merge(e.clone()),
+                None => e.clone(),
+            });
+        }
+        res
+    }
+    // parse P from the current position to the end of input.
+    // if P fails or stops early, the error is the furthest failure seen, which is usually the cause.
+    // the error becomes a diagnostic by ParseError::to_diagnostic.
+    pub fn parse_complete<P: SyntaxTree<T>>(&mut self) -> Result<P, ParseError> {
+        let e = match self.parse::<P>() {
+            ParserResult::Ok(t) if self.is_eof() => return Ok(t),
+            ParserResult::Ok(_) => self.error(EOF),
+            ParserResult::Fail(e) => e,
+            ParserResult::Err(e) => return Err(e),
+        };
+        Err(match &self.furthest {
+            Some(furthest) => e.merge(furthest.clone()),
+            None => e,
+        })
+    }
+    // the furthest failure seen so far
+    pub fn furthest_error(&self) -> Option<&ParseError> {
+        self.furthest.as_ref()
+    }
+    // run f at most once per (position, P). the result, the end position and the errors
+    // recorded during f are replayed on later calls.
+    //
+    // direct left recursion is supported by seed growing (Warth et al.): a recursive call at the
+    // same position fails at first, and f is then rerun with the last result as the seed for as
+    // long as it consumes more tokens.
+    pub fn memoize<P, F>(&mut self, f: F) -> ParserResult<P>
+    where
+        P: SyntaxTree<T> + Clone + 'static,
+        F: Fn(&mut Tokens<T>) -> ParserResult<P>,
+    {
+        let start = self.i;
+        let key = (start, TypeId::of::<P>());
+        if let Some(detected) = self.heads.get_mut(&key) {
+            *detected = true;
+        }
+        if let Some(entry) = self.memo.get(&key) {
+            let entry = entry.as_any().downcast_ref::<MemoEntry<P>>().unwrap();
+            let res = entry.res.clone();
+            self.i = entry.end;
+            self.errors.extend(entry.errors.iter().cloned());
+            self.look(entry.reach.saturating_sub(1));
+            return res;
+        }
+        let seed = MemoEntry::<P> {
+            res: ParserResult::Fail(self.error(P::NAME)),
+            end: start,
+            reach: start,
+            errors: vec![],
+        };
+        self.memo.insert(key, Box::new(seed));
+        self.heads.insert(key, false);
+        let outer_reach = self.reach.replace(start);
+        let errors_len = self.errors.len();
+        let mut res = f(self);
+        let mut end = self.i;
+        let detected = self.heads.remove(&key).unwrap();
+        if detected && res.is_ok() {
+            loop {
+                let errors = self.errors.split_off(errors_len);
+                let entry = MemoEntry {
+                    res: res.clone(),
+                    end,
+                    reach: self.reach.get(),
+                    errors: errors.clone(),
+                };
+                self.memo.insert(key, Box::new(entry));
+                // other results at this position may depend on the seed
+                let heads = &self.heads;
+                self.memo
+                    .retain(|k, _| k.0 != start || *k == key || heads.contains_key(k));
+                self.i = start;
+                self.heads.insert(key, false);
+                let r = f(self);
+                self.heads.remove(&key);
+                if !r.is_ok() || self.i <= end {
+                    self.errors.truncate(errors_len);
+                    self.errors.extend(errors);
+                    break;
+                }
+                res = r;
+                end = self.i;
+            }
+            self.i = end;
+        }
+        let reach = self.reach.get();
+        let entry = MemoEntry {
+            res: res.clone(),
+            end,
+            reach,
+            errors: self.errors[errors_len..].to_vec(),
+        };
+        self.memo.insert(key, Box::new(entry));
+        self.reach.set(reach.max(outer_reach));
+        res
+    }
+    pub fn clear_memo(&mut self) {
+        self.memo.clear();
+    }
+    pub fn error(&self, expected: &'static str) -> ParseError {
+        let span = match self.ts.get(self.i) {
+            Some(t) => t.span(),
+            None => self.span_from(self.i),
+        };
+        let mut set = BTreeSet::new();
+        set.insert(expected);
+        ParseError {
+            i: self.i,
+            span,
+            expected: set,
+        }
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct SyntaxTreeId(usize);
+// marker for ids of syntax trees, so that they can be used as keys of neco_table::SubTable
+#[derive(Debug, Clone)]
+pub enum SyntaxTreeNode {}
 
-const NEXT_SYNTAX_TREE_ID: OnceCell<SyntaxTreeId> = OnceCell::new();
-pub fn gen_next_syntax_tree_id() -> SyntaxTreeId {
-    let res = NEXT_SYNTAX_TREE_ID.get_or_init(|| SyntaxTreeId(1)).clone();
-    NEXT_SYNTAX_TREE_ID.set(SyntaxTreeId(res.0 + 1)).unwrap();
-    res.clone()
+pub type SyntaxTreeId = Id<SyntaxTreeNode>;
+pub type SyntaxTreeIdManager = IdManager<SyntaxTreeNode>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    i: usize,
+    span: Span,
+    expected: BTreeSet<&'static str>,
+}
+
+impl ParseError {
+    pub fn i(&self) -> usize {
+        self.i
+    }
+    pub fn span(&self) -> Span {
+        self.span
+    }
+    pub fn expected(&self) -> &BTreeSet<&'static str> {
+        &self.expected
+    }
+    pub(crate) fn shift(&mut self, token_delta: isize, delta: isize) {
+        self.i = self
+            .i
+            .checked_add_signed(token_delta)
+            .expect("parse error shifted before the first token");
+        self.span = self.span.shift(delta);
+    }
+    // keep the error which reached furthest, and union the expectations on a tie
+    pub fn merge(mut self, other: ParseError) -> ParseError {
+        if other.i > self.i {
+            return other;
+        }
+        if other.i == self.i {
+            self.expected.extend(other.expected);
+        }
+        self
+    }
 }
 
 #[derive(Debug, Clone)]
 pub enum ParserResult<T> {
     Ok(T),
-    Fail,
-    Err,
+    Fail(ParseError),
+    Err(ParseError),
 }
 
 impl<T> ParserResult<T> {
@@ -114,25 +748,39 @@ impl<T> ParserResult<T> {
         matches!(self, ParserResult::Ok(_))
     }
     pub fn is_fail(&self) -> bool {
-        matches!(self, ParserResult::Fail)
+        matches!(self, ParserResult::Fail(_))
     }
     pub fn is_err(&self) -> bool {
-        matches!(self, ParserResult::Err)
+        matches!(self, ParserResult::Err(_))
+    }
+    pub fn error(&self) -> Option<&ParseError> {
+        match self {
+            ParserResult::Ok(_) => None,
+            ParserResult::Fail(e) | ParserResult::Err(e) => Some(e),
+        }
     }
 }
 
+#[derive(Debug, Clone)]
 pub struct Rep0<T> {
     pub id: SyntaxTreeId,
+    pub span: Span,
     pub ts: Vec<T>,
+    pub errors: Vec<ErrorNode>,
 }
 
+#[derive(Debug, Clone)]
 pub struct Rep1<T> {
     pub id: SyntaxTreeId,
+    pub span: Span,
     pub ts: Vec<T>,
+    pub errors: Vec<ErrorNode>,
 }
 
+#[derive(Debug, Clone)]
 pub struct Optional<T> {
     pub id: SyntaxTreeId,
+    pub span: Span,
     pub inner: Option<T>,
 }
 
@@ -140,95 +788,517 @@ pub trait SyntaxTree<T: TokenSet>
 where
     Self: Sized,
 {
+    // how parse errors call Self, which is the #[name] or the type name for derived trees
+    const NAME: &'static str;
     fn parse(tokens: &mut Tokens<T>) -> ParserResult<Self>;
     fn id(&self) -> SyntaxTreeId;
+    // from the first to the last consumed token
+    fn span(&self) -> Span;
+    // move the spans by delta chars, when the tree is reused after an edit before it
+    fn shift(&mut self, delta: isize);
+    // called in recovery mode after Self failed, at the position Self began.
+    // returns false if it can't resync, and a recovery which consumes nothing is a failure too.
+    fn recover(_tokens: &mut Tokens<T>) -> bool {
+        false
+    }
+}
+
+impl<S: TokenSet, T: SyntaxTree<S>> SyntaxTree<S> for Box<T> {
+    const NAME: &'static str = T::NAME;
+    fn parse(tokens: &mut Tokens<S>) -> ParserResult<Self> {
+        match tokens.parse::<T>() {
+            ParserResult::Ok(t) => ParserResult::Ok(Box::new(t)),
+            ParserResult::Fail(e) => ParserResult::Fail(e),
+            ParserResult::Err(e) => ParserResult::Err(e),
+        }
+    }
+    fn id(&self) -> SyntaxTreeId {
+        self.as_ref().id()
+    }
+    fn span(&self) -> Span {
+        self.as_ref().span()
+    }
+    fn shift(&mut self, delta: isize) {
+        self.as_mut().shift(delta);
+    }
+    fn recover(tokens: &mut Tokens<S>) -> bool {
+        T::recover(tokens)
+    }
+}
+
+// what Eof expects
+pub const EOF: &str = "EOF";
+
+// succeeds only at the end of input, without consuming anything
+#[derive(Debug, Clone)]
+pub struct Eof {
+    pub id: SyntaxTreeId,
+    pub span: Span,
+}
+
+impl<S: TokenSet> SyntaxTree<S> for Eof {
+    const NAME: &'static str = EOF;
+    fn parse(tokens: &mut Tokens<S>) -> ParserResult<Self> {
+        if tokens.is_eof() {
+            ParserResult::Ok(Eof {
+                id: tokens.gen_id(),
+                span: tokens.span_from(tokens.get_i()),
+            })
+        } else {
+            ParserResult::Fail(tokens.error(EOF))
+        }
+    }
+    fn id(&self) -> SyntaxTreeId {
+        self.id
+    }
+    fn span(&self) -> Span {
+        self.span
+    }
+    fn shift(&mut self, delta: isize) {
+        self.span = self.span.shift(delta);
+    }
+}
+
+// a failed item which was skipped in recovery mode. `index` is where it would have been in `ts`.
+#[derive(Debug, Clone)]
+pub struct ErrorNode {
+    pub id: SyntaxTreeId,
+    pub index: usize,
+    pub error: ParseError,
+}
+
+// the error to report if T was skipped
+fn try_recover<S: TokenSet, T: SyntaxTree<S>>(
+    tokens: &mut Tokens<S>,
+    initial_i: usize,
+    e: &ParseError,
+) -> Option<ParseError> {
+    if !tokens.recovery() {
+        return None;
+    }
+    tokens.set_i(initial_i);
+    if !T::recover(tokens) || tokens.get_i() == initial_i {
+        tokens.set_i(initial_i);
+        return None;
+    }
+    // the furthest failure is usually the cause, as in parse_complete. it is reported here and
+    // not again. a failure beyond the skipped tokens was of another alternative.
+    let e = match tokens.furthest.take() {
+        Some(furthest) if furthest.i() < tokens.get_i() => e.clone().merge(furthest),
+        furthest => {
+            tokens.furthest = furthest;
+            e.clone()
+        }
+    };
+    tokens.push_error(e.clone());
+    Some(e)
+}
+
+fn shift_repetition<S: TokenSet, T: SyntaxTree<S>>(
+    span: &mut Span,
+    ts: &mut [T],
+    errors: &mut [ErrorNode],
+    delta: isize,
+) {
+    *span = span.shift(delta);
+    for t in ts {
+        t.shift(delta);
+    }
+    for error in errors {
+        error.error.shift(0, delta);
+    }
 }
 
 impl<S: TokenSet, T: SyntaxTree<S>> SyntaxTree<S> for Rep0<T> {
+    const NAME: &'static str = T::NAME;
     fn parse(tokens: &mut Tokens<S>) -> ParserResult<Self> {
+        let begin_i = tokens.get_i();
         let mut res = vec![];
-        while let ParserResult::Ok(t) = tokens.parse::<T>() {
-            res.push(t);
+        let mut errors = vec![];
+        loop {
+            let initial_i = tokens.get_i();
+            match tokens.parse::<T>() {
+                ParserResult::Ok(t) => res.push(t),
+                ParserResult::Fail(e) => match try_recover::<S, T>(tokens, initial_i, &e) {
+                    Some(error) => errors.push(ErrorNode {
+                        id: tokens.gen_id(),
+                        index: res.len(),
+                        error,
+                    }),
+                    None => break,
+                },
+                ParserResult::Err(e) => return ParserResult::Err(e),
+            }
         }
         ParserResult::Ok(Rep0 {
-            id: gen_next_syntax_tree_id(),
+            id: tokens.gen_id(),
+            span: tokens.span_from(begin_i),
             ts: res,
+            errors,
         })
     }
     fn id(&self) -> SyntaxTreeId {
         self.id
     }
+    fn span(&self) -> Span {
+        self.span
+    }
+    fn shift(&mut self, delta: isize) {
+        shift_repetition(&mut self.span, &mut self.ts, &mut self.errors, delta);
+    }
 }
 
 impl<S: TokenSet, T: SyntaxTree<S>> SyntaxTree<S> for Rep1<T> {
+    const NAME: &'static str = T::NAME;
     fn parse(tokens: &mut Tokens<S>) -> ParserResult<Self> {
+        let begin_i = tokens.get_i();
         let mut res = vec![];
-        while let ParserResult::Ok(t) = tokens.parse::<T>() {
-            res.push(t);
-        }
-        if res.len() >= 1 {
+        let mut errors = vec![];
+        let error = loop {
+            let initial_i = tokens.get_i();
+            match tokens.parse::<T>() {
+                ParserResult::Ok(t) => res.push(t),
+                ParserResult::Fail(e) => match try_recover::<S, T>(tokens, initial_i, &e) {
+                    Some(error) => errors.push(ErrorNode {
+                        id: tokens.gen_id(),
+                        index: res.len(),
+                        error,
+                    }),
+                    None => break e,
+                },
+                ParserResult::Err(e) => return ParserResult::Err(e),
+            }
+        };
+        if !res.is_empty() || !errors.is_empty() {
             ParserResult::Ok(Rep1 {
-                id: gen_next_syntax_tree_id(),
+                id: tokens.gen_id(),
+                span: tokens.span_from(begin_i),
                 ts: res,
+                errors,
             })
         } else {
-            ParserResult::Fail
+            ParserResult::Fail(error)
         }
     }
     fn id(&self) -> SyntaxTreeId {
         self.id
     }
+    fn span(&self) -> Span {
+        self.span
+    }
+    fn shift(&mut self, delta: isize) {
+        shift_repetition(&mut self.span, &mut self.ts, &mut self.errors, delta);
+    }
 }
 
 impl<S: TokenSet, T: SyntaxTree<S>> SyntaxTree<S> for Optional<T> {
+    const NAME: &'static str = T::NAME;
     fn parse(tokens: &mut Tokens<S>) -> ParserResult<Self> {
-        if let ParserResult::Ok(t) = tokens.parse::<T>() {
-            ParserResult::Ok(Optional {
-                id: gen_next_syntax_tree_id(),
+        let begin_i = tokens.get_i();
+        match tokens.parse::<T>() {
+            ParserResult::Ok(t) => ParserResult::Ok(Optional {
+                id: tokens.gen_id(),
+                span: tokens.span_from(begin_i),
                 inner: Some(t),
-            })
-        } else {
-            ParserResult::Ok(Optional {
-                id: gen_next_syntax_tree_id(),
+            }),
+            ParserResult::Fail(_) => ParserResult::Ok(Optional {
+                id: tokens.gen_id(),
+                span: tokens.span_from(begin_i),
                 inner: None,
-            })
+            }),
+            ParserResult::Err(e) => ParserResult::Err(e),
         }
     }
     fn id(&self) -> SyntaxTreeId {
         self.id
     }
+    fn span(&self) -> Span {
+        self.span
+    }
+    fn shift(&mut self, delta: isize) {
+        self.span = self.span.shift(delta);
+        if let Some(t) = &mut self.inner {
+            t.shift(delta);
+        }
+    }
 }
 
 // accept: (empty), T, T P, T P T, T P T P, T P T P T, ...
+#[derive(Debug, Clone)]
 pub struct Punctuated<T, P> {
     pub id: SyntaxTreeId,
+    pub span: Span,
     pub ts: Vec<T>,
     pub ps: Vec<P>,
+    pub errors: Vec<ErrorNode>,
 }
 
 impl<S: TokenSet, T: SyntaxTree<S>, P: SyntaxTree<S>> SyntaxTree<S> for Punctuated<T, P> {
+    const NAME: &'static str = T::NAME;
     fn parse(tokens: &mut Tokens<S>) -> ParserResult<Self> {
+        let begin_i = tokens.get_i();
         let mut ts = vec![];
         let mut ps = vec![];
+        let mut errors = vec![];
         loop {
-            if let ParserResult::Ok(t) = tokens.parse::<T>() {
-                ts.push(t);
-            } else {
-                break;
+            let initial_i = tokens.get_i();
+            match tokens.parse::<T>() {
+                ParserResult::Ok(t) => ts.push(t),
+                ParserResult::Fail(e) => match try_recover::<S, T>(tokens, initial_i, &e) {
+                    Some(error) => {
+                        errors.push(ErrorNode {
+                            id: tokens.gen_id(),
+                            index: ts.len(),
+                            error,
+                        });
+                        continue;
+                    }
+                    None => break,
+                },
+                ParserResult::Err(e) => return ParserResult::Err(e),
             }
-            if let ParserResult::Ok(p) = tokens.parse::<P>() {
-                ps.push(p);
-            } else {
-                break;
+            match tokens.parse::<P>() {
+                ParserResult::Ok(p) => ps.push(p),
+                ParserResult::Fail(_) => break,
+                ParserResult::Err(e) => return ParserResult::Err(e),
             }
         }
         ParserResult::Ok(Punctuated {
-            id: gen_next_syntax_tree_id(),
+            id: tokens.gen_id(),
+            span: tokens.span_from(begin_i),
             ts,
             ps,
+            errors,
         })
     }
     fn id(&self) -> SyntaxTreeId {
         self.id
     }
+    fn span(&self) -> Span {
+        self.span
+    }
+    fn shift(&mut self, delta: isize) {
+        shift_repetition(&mut self.span, &mut self.ts, &mut self.errors, delta);
+        for p in &mut self.ps {
+            p.shift(delta);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Associativity {
+    Left,
+    Right,
+}
+
+// operators with a higher precedence bind tighter
+pub trait InfixOperator {
+    fn precedence(&self) -> usize;
+    fn associativity(&self) -> Associativity;
+}
+
+// accept: T, T O T, T O T O T, ... and build the tree by the precedence of O
+#[derive(Debug, Clone)]
+pub enum Infix<T, O> {
+    Operand(T),
+    Binary {
+        id: SyntaxTreeId,
+        lhs: Box<Infix<T, O>>,
+        op: O,
+        rhs: Box<Infix<T, O>>,
+    },
+}
+
+fn parse_infix<S, T, O>(tokens: &mut Tokens<S>, min_precedence: usize) -> ParserResult<Infix<T, O>>
+where
+    S: TokenSet,
+    T: SyntaxTree<S>,
+    O: SyntaxTree<S> + InfixOperator,
+{
+    let initial_i = tokens.get_i();
+    let mut lhs = match tokens.parse::<T>() {
+        ParserResult::Ok(t) => Infix::Operand(t),
+        ParserResult::Fail(e) => return ParserResult::Fail(e),
+        ParserResult::Err(e) => return ParserResult::Err(e),
+    };
+    loop {
+        let op_i = tokens.get_i();
+        let op = match tokens.parse::<O>() {
+            ParserResult::Ok(op) => op,
+            ParserResult::Fail(_) => break,
+            ParserResult::Err(e) => return ParserResult::Err(e),
+        };
+        let precedence = op.precedence();
+        if precedence < min_precedence {
+            tokens.set_i(op_i);
+            break;
+        }
+        let next_min_precedence = match op.associativity() {
+            Associativity::Left => precedence + 1,
+            Associativity::Right => precedence,
+        };
+        let rhs = match parse_infix(tokens, next_min_precedence) {
+            ParserResult::Ok(t) => t,
+            ParserResult::Fail(e) => {
+                tokens.set_i(initial_i);
+                return ParserResult::Fail(e);
+            }
+            ParserResult::Err(e) => return ParserResult::Err(e),
+        };
+        lhs = Infix::Binary {
+            id: tokens.gen_id(),
+            lhs: Box::new(lhs),
+            op,
+            rhs: Box::new(rhs),
+        };
+    }
+    ParserResult::Ok(lhs)
+}
+
+impl<S: TokenSet, T: SyntaxTree<S>, O: SyntaxTree<S> + InfixOperator> SyntaxTree<S>
+    for Infix<T, O>
+{
+    const NAME: &'static str = T::NAME;
+    fn parse(tokens: &mut Tokens<S>) -> ParserResult<Self> {
+        parse_infix(tokens, 0)
+    }
+    fn id(&self) -> SyntaxTreeId {
+        match self {
+            Infix::Operand(t) => t.id(),
+            Infix::Binary { id, .. } => *id,
+        }
+    }
+    fn span(&self) -> Span {
+        match self {
+            Infix::Operand(t) => t.span(),
+            Infix::Binary { lhs, op, rhs, .. } => Span::cover(&[lhs.span(), op.span(), rhs.span()]),
+        }
+    }
+    fn shift(&mut self, delta: isize) {
+        match self {
+            Infix::Operand(t) => t.shift(delta),
+            Infix::Binary { lhs, op, rhs, .. } => {
+                lhs.shift(delta);
+                op.shift(delta);
+                rhs.shift(delta);
+            }
+        }
+    }
+}
+
+pub fn parse_token_value<S, V>(tokens: &mut Tokens<S>) -> ParserResult<V>
+where
+    S: TokenSet,
+    V: TokenValue,
+    V::Base: SyntaxTree<S>,
+{
+    let initial_i = tokens.get_i();
+    // not tokens.parse, so that a failure of the base is not recorded besides V::VALUE
+    match V::Base::parse(tokens) {
+        ParserResult::Ok(base) if base.text().as_deref() == Some(V::VALUE) => {
+            ParserResult::Ok(V::from_base(base))
+        }
+        ParserResult::Ok(_) | ParserResult::Fail(_) => {
+            tokens.set_i(initial_i);
+            ParserResult::Fail(tokens.error(V::VALUE))
+        }
+        ParserResult::Err(e) => ParserResult::Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn project() -> (Project, ProgramFileId) {
+        let mut project = Project::new();
+        let id = project.add_file(
+            PathBuf::from("a.felis"),
+            "let x = 1;\r\nlet y =\n  x + 2;\n".chars().collect(),
+        );
+        (project, id)
+    }
+
+    #[test]
+    fn test_span_cover() {
+        let id = ProgramFileId(1);
+        let span = |begin, end| Span::new_with_span(id, begin, end);
+        assert_eq!(
+            Span::cover(&[span(1, 1), span(2, 4), span(5, 7), span(8, 8)]),
+            span(2, 7)
+        );
+        assert_eq!(Span::cover(&[span(3, 3), span(5, 5)]), span(3, 3));
+        assert_eq!(Span::cover(&[]), Span::new());
+    }
+
+    #[test]
+    #[should_panic(expected = "span shifted before the start of the file")]
+    fn test_span_shift_underflow() {
+        let span = Span::new_with_span(ProgramFileId(1), 2, 4);
+        assert_eq!(span.shift(-2), Span::new_with_span(ProgramFileId(1), 0, 2));
+        span.shift(-3);
+    }
+
+    #[test]
+    fn test_line_column() {
+        let (project, id) = project();
+        let file = project.get_file(id).unwrap();
+        assert_eq!(file.line_count(), 4);
+        assert_eq!(file.line_column(0), LineColumn { line: 1, column: 1 });
+        assert_eq!(file.line_column(4), LineColumn { line: 1, column: 5 });
+        assert_eq!(file.line_column(12), LineColumn { line: 2, column: 1 });
+        assert_eq!(file.line_column(22), LineColumn { line: 3, column: 3 });
+        assert_eq!(file.line_column(29), LineColumn { line: 4, column: 1 });
+        assert_eq!(
+            file.line(1).unwrap().iter().collect::<String>(),
+            "let x = 1;"
+        );
+        assert_eq!(file.line(3).unwrap().iter().collect::<String>(), "  x + 2;");
+        assert_eq!(file.line(4).unwrap().len(), 0);
+        assert!(file.line(5).is_none());
+    }
+
+    #[test]
+    fn test_load_dir() {
+        let dir =
+            std::env::temp_dir().join(format!("neco-syn-test-load-dir-{}", std::process::id()));
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("b.felis"), "let b = 2;").unwrap();
+        fs::write(dir.join("a.felis"), "let a = 1;").unwrap();
+        fs::write(dir.join("sub").join("c.felis"), "let c = 3;").unwrap();
+        fs::write(dir.join("memo.txt"), "not felis").unwrap();
+        let mut project = Project::new();
+        let ids = project.load_dir(&dir).unwrap();
+        assert_eq!(ids.len(), 3);
+        let paths: Vec<_> = ids
+            .iter()
+            .map(|id| project.get_file(*id).unwrap().path().to_path_buf())
+            .collect();
+        assert_eq!(
+            paths,
+            vec![
+                dir.join("a.felis"),
+                dir.join("b.felis"),
+                dir.join("sub").join("c.felis")
+            ]
+        );
+        let file = project.get_file_by_path(dir.join("b.felis")).unwrap();
+        assert_eq!(file.program_file_id(), ids[1]);
+        assert_eq!(file.body().iter().collect::<String>(), "let b = 2;");
+        assert!(project.get_file_by_path(dir.join("memo.txt")).is_none());
+        assert!(project.load_file(dir.join("none.felis")).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_location_and_snippet() {
+        let (project, id) = project();
+        let span = Span::new_with_span(id, 16, 27);
+        let location = project.location(span).unwrap();
+        assert_eq!(location.begin, LineColumn { line: 2, column: 5 });
+        assert_eq!(location.end, LineColumn { line: 3, column: 8 });
+        assert_eq!(location.to_string(), "a.felis:2:5");
+        assert_eq!(project.snippet(span).unwrap(), "y =\n  x + 2");
+        assert!(project.location(Span::new()).is_none());
+    }
 }
@@ -0,0 +1,150 @@
+// printing syntax trees back to source text.
+//
+// #[derive(ToSource)] prints a token by its text, and a tree by its fields in order.
+// the printer puts the separator chosen by the spacing rule between two tokens.
+
+use crate::{Eof, Infix, Optional, Punctuated, Rep0, Rep1};
+
+// the separator between two adjacent tokens
+pub type Spacing = fn(prev: &str, next: &str) -> &'static str;
+
+// a space between tokens, except around brackets and before separators
+pub fn default_spacing(prev: &str, next: &str) -> &'static str {
+    if prev.ends_with(['(', '[']) || next.starts_with([')', ']', ',', ';']) {
+        ""
+    } else {
+        " "
+    }
+}
+
+// a space only where two words would otherwise be joined
+pub fn compact_spacing(prev: &str, next: &str) -> &'static str {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    match (prev.chars().next_back(), next.chars().next()) {
+        (Some(a), Some(b)) if is_word(a) && is_word(b) => " ",
+        _ => "",
+    }
+}
+
+pub struct Printer {
+    out: String,
+    prev: Option<String>,
+    spacing: Spacing,
+}
+
+impl Default for Printer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Printer {
+    pub fn new() -> Printer {
+        Printer::with_spacing(default_spacing)
+    }
+    pub fn with_spacing(spacing: Spacing) -> Printer {
+        Printer {
+            out: String::new(),
+            prev: None,
+            spacing,
+        }
+    }
+    pub fn token(&mut self, text: &str) {
+        if let Some(prev) = &self.prev {
+            self.out.push_str((self.spacing)(prev, text));
+        }
+        self.out.push_str(text);
+        self.prev = Some(text.to_string());
+    }
+    pub fn finish(self) -> String {
+        self.out
+    }
+}
+
+pub trait ToSource {
+    fn to_source(&self, printer: &mut Printer);
+    fn to_source_string(&self) -> String {
+        let mut printer = Printer::new();
+        self.to_source(&mut printer);
+        printer.finish()
+    }
+}
+
+impl<T: ToSource> ToSource for Box<T> {
+    fn to_source(&self, printer: &mut Printer) {
+        self.as_ref().to_source(printer);
+    }
+}
+
+impl<T: ToSource> ToSource for Rep0<T> {
+    fn to_source(&self, printer: &mut Printer) {
+        for t in &self.ts {
+            t.to_source(printer);
+        }
+    }
+}
+
+impl<T: ToSource> ToSource for Rep1<T> {
+    fn to_source(&self, printer: &mut Printer) {
+        for t in &self.ts {
+            t.to_source(printer);
+        }
+    }
+}
+
+impl<T: ToSource> ToSource for Optional<T> {
+    fn to_source(&self, printer: &mut Printer) {
+        if let Some(t) = &self.inner {
+            t.to_source(printer);
+        }
+    }
+}
+
+impl<T: ToSource, P: ToSource> ToSource for Punctuated<T, P> {
+    fn to_source(&self, printer: &mut Printer) {
+        let mut ps = self.ps.iter();
+        for t in &self.ts {
+            t.to_source(printer);
+            if let Some(p) = ps.next() {
+                p.to_source(printer);
+            }
+        }
+    }
+}
+
+impl<T: ToSource, O: ToSource> ToSource for Infix<T, O> {
+    fn to_source(&self, printer: &mut Printer) {
+        match self {
+            Infix::Operand(t) => t.to_source(printer),
+            Infix::Binary { lhs, op, rhs, .. } => {
+                lhs.to_source(printer);
+                op.to_source(printer);
+                rhs.to_source(printer);
+            }
+        }
+    }
+}
+
+impl ToSource for Eof {
+    fn to_source(&self, _printer: &mut Printer) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn print(spacing: Spacing, texts: &[&str]) -> String {
+        let mut printer = Printer::with_spacing(spacing);
+        for text in texts {
+            printer.token(text);
+        }
+        printer.finish()
+    }
+
+    #[test]
+    fn test_spacing() {
+        let texts = ["let", "x", "=", "f", "(", "1", ",", "2", ")", ";"];
+        assert_eq!(print(default_spacing, &texts), "let x = f (1, 2);");
+        assert_eq!(print(compact_spacing, &texts), "let x=f(1,2);");
+    }
+}
@@ -0,0 +1,141 @@
+// traversal of syntax trees.
+//
+// #[derive(SyntaxTree)] implements Walk and WalkMut, which accept the children of a node in the order of the source.
+// visitor! declares Visit and VisitMut traits with a method per node type,
+// and implements Accept and AcceptMut, which call the method for the node.
+
+use crate::{Eof, Infix, Optional, Punctuated, Rep0, Rep1};
+
+pub trait Walk<V: ?Sized> {
+    fn walk(&self, v: &mut V);
+}
+
+pub trait WalkMut<V: ?Sized> {
+    fn walk_mut(&mut self, v: &mut V);
+}
+
+pub trait Accept<V: ?Sized> {
+    fn accept(&self, v: &mut V);
+}
+
+pub trait AcceptMut<V: ?Sized> {
+    fn accept_mut(&mut self, v: &mut V);
+}
+
+impl<V: ?Sized, T: Accept<V>> Accept<V> for Box<T> {
+    fn accept(&self, v: &mut V) {
+        self.as_ref().accept(v);
+    }
+}
+
+impl<V: ?Sized, T: AcceptMut<V>> AcceptMut<V> for Box<T> {
+    fn accept_mut(&mut self, v: &mut V) {
+        self.as_mut().accept_mut(v);
+    }
+}
+
+impl<V: ?Sized, T: Accept<V>> Accept<V> for Rep0<T> {
+    fn accept(&self, v: &mut V) {
+        for t in &self.ts {
+            t.accept(v);
+        }
+    }
+}
+
+impl<V: ?Sized, T: AcceptMut<V>> AcceptMut<V> for Rep0<T> {
+    fn accept_mut(&mut self, v: &mut V) {
+        for t in &mut self.ts {
+            t.accept_mut(v);
+        }
+    }
+}
+
+impl<V: ?Sized, T: Accept<V>> Accept<V> for Rep1<T> {
+    fn accept(&self, v: &mut V) {
+        for t in &self.ts {
+            t.accept(v);
+        }
+    }
+}
+
+impl<V: ?Sized, T: AcceptMut<V>> AcceptMut<V> for Rep1<T> {
+    fn accept_mut(&mut self, v: &mut V) {
+        for t in &mut self.ts {
+            t.accept_mut(v);
+        }
+    }
+}
+
+impl<V: ?Sized, T: Accept<V>> Accept<V> for Optional<T> {
+    fn accept(&self, v: &mut V) {
+        if let Some(t) = &self.inner {
+            t.accept(v);
+        }
+    }
+}
+
+impl<V: ?Sized, T: AcceptMut<V>> AcceptMut<V> for Optional<T> {
+    fn accept_mut(&mut self, v: &mut V) {
+        if let Some(t) = &mut self.inner {
+            t.accept_mut(v);
+        }
+    }
+}
+
+impl<V: ?Sized, T: Accept<V>, P: Accept<V>> Accept<V> for Punctuated<T, P> {
+    fn accept(&self, v: &mut V) {
+        let mut ps = self.ps.iter();
+        for t in &self.ts {
+            t.accept(v);
+            if let Some(p) = ps.next() {
+                p.accept(v);
+            }
+        }
+    }
+}
+
+impl<V: ?Sized, T: AcceptMut<V>, P: AcceptMut<V>> AcceptMut<V> for Punctuated<T, P> {
+    fn accept_mut(&mut self, v: &mut V) {
+        let mut ps = self.ps.iter_mut();
+        for t in &mut self.ts {
+            t.accept_mut(v);
+            if let Some(p) = ps.next() {
+                p.accept_mut(v);
+            }
+        }
+    }
+}
+
+impl<V: ?Sized, T: Accept<V>, O: Accept<V>> Accept<V> for Infix<T, O> {
+    fn accept(&self, v: &mut V) {
+        match self {
+            Infix::Operand(t) => t.accept(v),
+            Infix::Binary { lhs, op, rhs, .. } => {
+                lhs.accept(v);
+                op.accept(v);
+                rhs.accept(v);
+            }
+        }
+    }
+}
+
+impl<V: ?Sized, T: AcceptMut<V>, O: AcceptMut<V>> AcceptMut<V> for Infix<T, O> {
+    fn accept_mut(&mut self, v: &mut V) {
+        match self {
+            Infix::Operand(t) => t.accept_mut(v),
+            Infix::Binary { lhs, op, rhs, .. } => {
+                lhs.accept_mut(v);
+                op.accept_mut(v);
+                rhs.accept_mut(v);
+            }
+        }
+    }
+}
+
+impl<V: ?Sized> Accept<V> for Eof {
+    fn accept(&self, _v: &mut V) {}
+}
+
+impl<V: ?Sized> AcceptMut<V> for Eof {
+    fn accept_mut(&mut self, _v: &mut V) {}
+}
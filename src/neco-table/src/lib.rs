@@ -9,10 +9,7 @@ pub struct Id<T> {
 
 impl<T> Clone for Id<T> {
     fn clone(&self) -> Self {
-        Id::<T> {
-            id: self.id,
-            phantom: PhantomData,
-        }
+        *self
     }
 }
 
@@ -38,6 +35,12 @@ pub struct IdManager<T> {
     phantom: PhantomData<fn() -> T>,
 }
 
+impl<T> Default for IdManager<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<T> IdManager<T> {
     pub fn new() -> IdManager<T> {
         IdManager::<T> {
@@ -60,6 +63,12 @@ pub struct MainTable<T> {
     map: HashMap<Id<T>, T>,
 }
 
+impl<T> Default for MainTable<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<T> MainTable<T> {
     pub fn new() -> MainTable<T> {
         MainTable::<T> {
@@ -84,6 +93,12 @@ pub struct SubTable<T, U> {
     map: HashMap<Id<T>, U>,
 }
 
+impl<T, U> Default for SubTable<T, U> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<T, U> SubTable<T, U> {
     pub fn new() -> SubTable<T, U> {
         SubTable::<T, U> {
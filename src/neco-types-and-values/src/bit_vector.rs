@@ -5,6 +5,12 @@ pub struct BitVector {
     deque: VecDeque<bool>,
 }
 
+impl Default for BitVector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl BitVector {
     pub fn new() -> BitVector {
         BitVector {
@@ -30,6 +36,28 @@ impl BitVector {
         self.deque.back()
     }
     pub fn len(&self) -> usize {
-        self.len()
+        self.deque.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.deque.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_len() {
+        let mut v = BitVector::new();
+        assert_eq!(v.len(), 0);
+        assert!(v.is_empty());
+        v.push_lsb(true);
+        v.push_msb(false);
+        v.push_lsb(false);
+        assert_eq!(v.len(), 3);
+        assert!(!v.is_empty());
+        assert_eq!(v.pop_msb(), Some(false));
+        assert_eq!(v.len(), 2);
     }
 }
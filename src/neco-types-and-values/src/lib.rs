@@ -1,8 +1,7 @@
 pub mod bit_vector;
 
-use std::rc::Rc;
-
 use bit_vector::BitVector;
+use neco_syn::Span;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct TypeId(usize);
@@ -12,6 +11,7 @@ pub struct AnnotationId(usize);
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Type {
+    Unit,
     Bool,
     Int(usize),
     Infer,
@@ -30,25 +30,48 @@ pub enum TypeRel {
     Same(AnnotationId, AnnotationId),
 }
 
+// found does not have the type of expected. the spans are the ones the annotations were created with.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TypeError {
+    Mismatch {
+        expected: AnnotationId,
+        found: AnnotationId,
+        expected_ty: Type,
+        found_ty: Type,
+        expected_span: Span,
+        found_span: Span,
+    },
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Annotator {
     annotates: Vec<Type>,
+    spans: Vec<Span>,
     type_rels: Vec<TypeRel>,
 }
 
+impl Default for Annotator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Annotator {
     pub fn new() -> Annotator {
         Annotator {
             annotates: vec![],
+            spans: vec![],
             type_rels: vec![],
         }
     }
-    pub fn create_annotation(&mut self, ty: Type) -> AnnotationId {
+    // span is the node which has the type, and is where mismatches are reported
+    pub fn create_annotation(&mut self, ty: Type, span: Span) -> AnnotationId {
         let res = AnnotationId(self.annotates.len());
         self.annotates.push(ty);
+        self.spans.push(span);
         res
     }
-    pub fn annotate(&mut self, default_integer_type: Type) {
+    pub fn annotate(&mut self, default_integer_type: Type) -> Result<(), Vec<TypeError>> {
         self.annotate_sub();
         for ty in self.annotates.iter_mut() {
             if *ty == Type::InferInteger {
@@ -56,6 +79,41 @@ impl Annotator {
             }
         }
         self.annotate_sub();
+        let errors = self.check();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+    fn check(&self) -> Vec<TypeError> {
+        let mut errors = vec![];
+        for rel in &self.type_rels {
+            match rel {
+                TypeRel::Same(id1, id2) => {
+                    let ty1 = &self.annotates[id1.0];
+                    let ty2 = &self.annotates[id2.0];
+                    let compatible = match (ty1, ty2) {
+                        (Type::Infer, _) | (_, Type::Infer) => true,
+                        (Type::InferInteger, Type::InferInteger)
+                        | (Type::InferInteger, Type::Int(_))
+                        | (Type::Int(_), Type::InferInteger) => true,
+                        (ty1, ty2) => ty1 == ty2,
+                    };
+                    if !compatible {
+                        errors.push(TypeError::Mismatch {
+                            expected: *id1,
+                            found: *id2,
+                            expected_ty: ty1.clone(),
+                            found_ty: ty2.clone(),
+                            expected_span: self.spans[id1.0],
+                            found_span: self.spans[id2.0],
+                        });
+                    }
+                }
+            }
+        }
+        errors
     }
     fn annotate_sub(&mut self) {
         let type_rels = self.type_rels.clone();
@@ -75,14 +133,23 @@ impl Annotator {
     }
     fn infer_same(&mut self, id1: AnnotationId, id2: AnnotationId) -> bool {
         match (&self.annotates[id1.0], &self.annotates[id2.0]) {
+            (Type::Unit, Type::Infer) => {
+                self.annotates[id2.0] = Type::Unit;
+                true
+            }
+            (Type::Infer, Type::Unit) => {
+                self.annotates[id1.0] = Type::Unit;
+                true
+            }
+            (Type::Unit, _) | (_, Type::Unit) => false,
             (Type::Bool, Type::Bool) => false,
-            (Type::Bool, Type::Int(_)) => panic!(),
+            (Type::Bool, Type::Int(_)) => false,
             (Type::Bool, Type::Infer) => {
                 self.annotates[id2.0] = Type::Bool;
                 true
             }
-            (Type::Bool, Type::InferInteger) => panic!(),
-            (Type::Int(_), Type::Bool) => panic!(),
+            (Type::Bool, Type::InferInteger) => false,
+            (Type::Int(_), Type::Bool) => false,
             (Type::Int(_), Type::Int(_)) => false,
             (Type::Int(_), Type::Infer) => {
                 self.annotates[id2.0] = self.annotates[id1.0].clone();
@@ -105,7 +172,7 @@ impl Annotator {
                 self.annotates[id1.0] = self.annotates[id2.0].clone();
                 true
             }
-            (Type::InferInteger, Type::Bool) => panic!(),
+            (Type::InferInteger, Type::Bool) => false,
             (Type::InferInteger, Type::Int(_)) => {
                 self.annotates[id1.0] = self.annotates[id2.0].clone();
                 true
@@ -125,41 +192,113 @@ impl Annotator {
     pub fn get_ty(&self, id: AnnotationId) -> Type {
         self.annotates[id.0].clone()
     }
+    pub fn get_span(&self, id: AnnotationId) -> Span {
+        self.spans[id.0]
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use neco_syn::Project;
+    use std::path::PathBuf;
+
+    fn span(begin: usize, end: usize) -> Span {
+        let id = Project::new().add_file(PathBuf::from("test.felis"), vec![]);
+        Span::new_with_span(id, begin, end)
+    }
 
     #[test]
     fn test_annotator_1() {
         // 1 + 2
         let mut annotator = Annotator::new();
-        let left = annotator.create_annotation(Type::InferInteger);
-        let right = annotator.create_annotation(Type::InferInteger);
-        let res = annotator.create_annotation(Type::Infer);
+        let left = annotator.create_annotation(Type::InferInteger, span(0, 1));
+        let right = annotator.create_annotation(Type::InferInteger, span(4, 5));
+        let res = annotator.create_annotation(Type::Infer, span(0, 5));
         annotator.same(&[res, left, right]);
-        annotator.annotate(Type::Int(32));
+        annotator.annotate(Type::Int(32)).unwrap();
         let ty_left = annotator.get_ty(left);
         let ty_right = annotator.get_ty(right);
         let ty_res = annotator.get_ty(res);
         assert_eq!(ty_left, Type::Int(32));
         assert_eq!(ty_right, Type::Int(32));
         assert_eq!(ty_res, Type::Int(32));
+        assert_eq!(annotator.get_span(right), span(4, 5));
     }
 
     #[test]
     fn test_annotator_2() {
         // x + x * 2
         let mut annotator = Annotator::new();
-        let id_x = annotator.create_annotation(Type::Infer);
-        let id_2 = annotator.create_annotation(Type::InferInteger);
-        let x_times_2 = annotator.create_annotation(Type::Infer);
+        let id_x = annotator.create_annotation(Type::Infer, span(0, 1));
+        let id_2 = annotator.create_annotation(Type::InferInteger, span(8, 9));
+        let x_times_2 = annotator.create_annotation(Type::Infer, span(4, 9));
         annotator.same(&[id_x, id_2, x_times_2]);
-        let res = annotator.create_annotation(Type::Infer);
+        let res = annotator.create_annotation(Type::Infer, span(0, 9));
         annotator.same(&[id_x, x_times_2, res]);
-        annotator.annotate(Type::Int(32));
+        annotator.annotate(Type::Int(32)).unwrap();
         assert_eq!(annotator.get_ty(id_x), Type::Int(32));
         assert_eq!(annotator.get_ty(id_2), Type::Int(32));
     }
+
+    #[test]
+    fn test_annotator_unit() {
+        // { x; } == 1
+        let mut annotator = Annotator::new();
+        let id_block = annotator.create_annotation(Type::Infer, span(0, 6));
+        let id_unit = annotator.create_annotation(Type::Unit, span(0, 6));
+        let id_1 = annotator.create_annotation(Type::InferInteger, span(10, 11));
+        annotator.same(&[id_unit, id_block, id_1]);
+        let errors = annotator.annotate(Type::Int(32)).unwrap_err();
+        assert_eq!(annotator.get_ty(id_block), Type::Unit);
+        assert_eq!(
+            errors,
+            vec![TypeError::Mismatch {
+                expected: id_block,
+                found: id_1,
+                expected_ty: Type::Unit,
+                found_ty: Type::Int(32),
+                expected_span: span(0, 6),
+                found_span: span(10, 11),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_annotator_mismatch() {
+        // x + true
+        let mut annotator = Annotator::new();
+        let id_x = annotator.create_annotation(Type::InferInteger, span(0, 1));
+        let id_true = annotator.create_annotation(Type::Bool, span(4, 8));
+        annotator.same(&[id_x, id_true]);
+        let errors = annotator.annotate(Type::Int(32)).unwrap_err();
+        assert_eq!(
+            errors,
+            vec![TypeError::Mismatch {
+                expected: id_x,
+                found: id_true,
+                expected_ty: Type::Int(32),
+                found_ty: Type::Bool,
+                expected_span: span(0, 1),
+                found_span: span(4, 8),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_annotator_int_width() {
+        // x: i32 = y: i64, twice
+        let mut annotator = Annotator::new();
+        let id_x = annotator.create_annotation(Type::Int(32), span(0, 1));
+        let id_y = annotator.create_annotation(Type::Int(64), span(4, 5));
+        let id_z = annotator.create_annotation(Type::Int(64), span(8, 9));
+        annotator.same(&[id_x, id_y]);
+        annotator.same(&[id_x, id_z]);
+        let errors = annotator.annotate(Type::Int(32)).unwrap_err();
+        let found: Vec<_> = errors
+            .iter()
+            .map(|TypeError::Mismatch { found_span, .. }| *found_span)
+            .collect();
+        assert_eq!(found, vec![span(4, 5), span(8, 9)]);
+    }
 }
@@ -1,3 +1,53 @@
+use neco::ErrorFormat;
+use neco_syn::Project;
+use std::path::PathBuf;
+
 fn main() {
-    println!("Hello, world!");
+    let mut error_format = ErrorFormat::Human;
+    let mut run = false;
+    let mut paths = vec![];
+    for arg in std::env::args().skip(1) {
+        if let Some(format) = arg.strip_prefix("--error-format=") {
+            error_format = match format {
+                "human" => ErrorFormat::Human,
+                "json" => ErrorFormat::Json,
+                _ => {
+                    eprintln!("error: unknown error format `{}`", format);
+                    std::process::exit(2);
+                }
+            };
+        } else if arg == "--run" {
+            run = true;
+        } else {
+            paths.push(PathBuf::from(arg));
+        }
+    }
+    if paths.is_empty() {
+        eprintln!("usage: neco [--error-format=human|json] [--run] <path>...");
+        std::process::exit(2);
+    }
+    let mut project = Project::new();
+    let mut diagnostics = neco::load(&mut project, &paths);
+    let mut programs = vec![];
+    for file in project.files() {
+        let (program, file_diagnostics) = neco::compile(file);
+        diagnostics.extend(file_diagnostics);
+        programs.extend(program);
+    }
+    eprint!("{}", neco::emit(&project, &diagnostics, error_format));
+    if diagnostics.iter().any(|d| d.is_error()) {
+        std::process::exit(1);
+    }
+    // run the main of every file
+    if run {
+        for program in &programs {
+            match neco::run(program) {
+                Ok(value) => println!("{}", value),
+                Err(e) => {
+                    eprintln!("error: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+    }
 }
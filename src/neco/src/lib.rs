@@ -0,0 +1,137 @@
+pub mod lower;
+pub mod typeck;
+
+use neco_ir::{interpret, Program, Value};
+use neco_syn::{diagnostic::Diagnostic, lang::felis, ProgramFile, Project, Span};
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorFormat {
+    Human,
+    Json,
+}
+
+// load files and directories given on the command line
+pub fn load(project: &mut Project, paths: &[PathBuf]) -> Vec<Diagnostic> {
+    let mut res = vec![];
+    for path in paths {
+        let loaded = if path.is_dir() {
+            project.load_dir(path).map(|_| ())
+        } else {
+            project.load_file(path).map(|_| ())
+        };
+        if let Err(e) = loaded {
+            res.push(
+                Diagnostic::error(
+                    format!("cannot read `{}`: {}", path.display(), e),
+                    Span::new(),
+                )
+                .with_code("E0000"),
+            );
+        }
+    }
+    res
+}
+
+// parse, type check and lower a file. there is no program if there are errors.
+pub fn compile(file: &ProgramFile) -> (Option<Program>, Vec<Diagnostic>) {
+    let (cst, mut diagnostics) = felis::parse(file);
+    let cst = match cst {
+        Some(cst) if diagnostics.is_empty() => cst,
+        _ => return (None, diagnostics),
+    };
+    let ast = felis::lower::lower(&cst);
+    let (types, errors) = typeck::typeck(&ast);
+    diagnostics.extend(errors);
+    if diagnostics.iter().any(|d| d.is_error()) {
+        return (None, diagnostics);
+    }
+    (Some(lower::lower(&ast, &types)), diagnostics)
+}
+
+pub fn check(project: &Project) -> Vec<Diagnostic> {
+    let mut res = vec![];
+    for file in project.files() {
+        res.extend(compile(file).1);
+    }
+    res
+}
+
+// run the `main` of a program
+pub fn run(program: &Program) -> Result<Value, String> {
+    let main = program
+        .function_by_name("main")
+        .ok_or_else(|| "no `main` function".to_string())?;
+    interpret::run(program, main, vec![]).map_err(|e| e.to_string())
+}
+
+pub fn emit(project: &Project, diagnostics: &[Diagnostic], error_format: ErrorFormat) -> String {
+    let mut res = String::new();
+    for diagnostic in diagnostics {
+        match error_format {
+            ErrorFormat::Human => {
+                res.push_str(&diagnostic.render(project));
+                res.push('\n');
+            }
+            ErrorFormat::Json => {
+                res.push_str(&diagnostic.to_json(project));
+                res.push('\n');
+            }
+        }
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_and_emit() {
+        let mut project = Project::new();
+        project.add_file(PathBuf::from("a.felis"), "let x = 1；".chars().collect());
+        let mut diagnostics = load(&mut project, &[PathBuf::from("/nonexistent/b.felis")]);
+        diagnostics.extend(check(&project));
+        assert_eq!(diagnostics.len(), 2);
+        let json = emit(&project, &diagnostics, ErrorFormat::Json);
+        let lines: Vec<_> = json.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with(
+            r#"{"severity":"error","code":"E0000","message":"cannot read `/nonexistent/b.felis`"#
+        ));
+        assert!(lines[1].starts_with(r#"{"severity":"error","code":"E0002","message":"unknown character `；`","span":{"path":"a.felis","begin":9,"end":10,"line_begin":1,"column_begin":10"#));
+        let human = emit(&project, &diagnostics[1..], ErrorFormat::Human);
+        assert!(human.starts_with("error[E0002]: unknown character `；`\n --> a.felis:1:10\n"));
+    }
+
+    #[test]
+    fn test_check_errors() {
+        let mut project = Project::new();
+        project.add_file(PathBuf::from("a.felis"), "fn f() {} let".chars().collect());
+        project.add_file(
+            PathBuf::from("b.felis"),
+            "fn f() -> i32 { true }".chars().collect(),
+        );
+        let diagnostics = check(&project);
+        let codes: Vec<_> = diagnostics.iter().map(|d| d.code.as_deref()).collect();
+        assert_eq!(codes, vec![Some("E0001"), Some("E0003")]);
+        let human = emit(&project, &diagnostics[1..], ErrorFormat::Human);
+        assert_eq!(
+            human,
+            "error[E0003]: mismatched types: expected `i32`, found `bool`\n --> b.felis:1:17\n  |\n1 | fn f() -> i32 { true }\n  |                 ^^^^\n  |           --- expected `i32` because of this\n  |\n\n"
+        );
+    }
+
+    #[test]
+    fn test_run() {
+        let mut project = Project::new();
+        let file_id = project.add_file(
+            PathBuf::from("a.felis"),
+            "fn main() -> i32 { f(20) + 1 }\nfn f(x: i32) -> i32 { x * 2 }"
+                .chars()
+                .collect(),
+        );
+        let program = compile(project.get_file(file_id).unwrap()).0.unwrap();
+        assert_eq!(run(&program), Ok(Value::Int(41)));
+    }
+}
@@ -0,0 +1,334 @@
+// lowering from the type checked Felis AST to neco-ir
+
+use crate::typeck::Types;
+use neco_ir::{BlockId, FunctionBuilder, FunctionId, Inst, Local, Program, Terminator, Value};
+use neco_syn::lang::felis::ast::{self, Ast};
+use neco_table::Id;
+use neco_types_and_values::Type;
+use std::collections::HashMap;
+
+// the ast has no errors
+pub fn lower(ast: &Ast, types: &Types) -> Program {
+    let mut fns = HashMap::new();
+    for (i, id) in ast.root.iter().enumerate() {
+        match &ast.item(*id).kind {
+            ast::ItemKind::Fn(fn_def) => {
+                fns.insert(fn_def.name.name.clone(), FunctionId(i));
+            }
+        }
+    }
+    let mut program = Program::new();
+    for id in &ast.root {
+        match &ast.item(*id).kind {
+            ast::ItemKind::Fn(fn_def) => {
+                let lowerer = FnLowerer {
+                    ast,
+                    types,
+                    fns: &fns,
+                    builder: FunctionBuilder::new(&fn_def.name.name, fn_def.params.len()),
+                    scopes: vec![],
+                    loops: vec![],
+                };
+                program.add_function(lowerer.fn_def(fn_def));
+            }
+        }
+    }
+    program
+}
+
+struct FnLowerer<'a> {
+    ast: &'a Ast,
+    types: &'a Types,
+    fns: &'a HashMap<String, FunctionId>,
+    builder: FunctionBuilder,
+    // the innermost scope is the last
+    scopes: Vec<HashMap<String, Local>>,
+    // the enclosing loops, the innermost last
+    loops: Vec<LoopTarget>,
+}
+
+struct LoopTarget {
+    continue_: BlockId,
+    break_: BlockId,
+    // where break puts its value. while has none.
+    value: Option<Local>,
+}
+
+impl<'a> FnLowerer<'a> {
+    fn fn_def(mut self, fn_def: &ast::FnDef) -> neco_ir::Function {
+        let mut scope = HashMap::new();
+        for (i, param) in fn_def.params.iter().enumerate() {
+            scope.insert(param.name.name.clone(), self.builder.param(i));
+        }
+        self.scopes.push(scope);
+        let res = self.block(&fn_def.body);
+        self.builder.terminate(Terminator::Return(res));
+        self.builder.finish()
+    }
+    fn constant(&mut self, value: Value) -> Local {
+        let dest = self.builder.new_local();
+        self.builder.push(Inst::Const { dest, value });
+        dest
+    }
+    fn copy(&mut self, src: Local) -> Local {
+        let dest = self.builder.new_local();
+        self.builder.push(Inst::Copy { dest, src });
+        dest
+    }
+    // the code after a jump is unreachable
+    fn jump_away(&mut self, terminator: Terminator) {
+        self.builder.terminate(terminator);
+        let block = self.builder.new_block();
+        self.builder.switch_to(block);
+    }
+    fn block(&mut self, block: &ast::Block) -> Local {
+        self.scopes.push(HashMap::new());
+        for stmt in &block.stmts {
+            self.stmt(stmt);
+        }
+        let res = match block.tail {
+            Some(tail) => self.expr(tail),
+            None => self.constant(Value::Unit),
+        };
+        self.scopes.pop();
+        res
+    }
+    fn stmt(&mut self, stmt: &ast::Stmt) {
+        match stmt {
+            // a variable has its own local, since it can be assigned
+            ast::Stmt::Let(let_) => {
+                let init = self.expr(let_.init);
+                let init = self.copy(init);
+                self.scopes
+                    .last_mut()
+                    .unwrap()
+                    .insert(let_.name.name.clone(), init);
+            }
+            ast::Stmt::Return(return_) => {
+                let value = match return_.value {
+                    Some(value) => self.expr(value),
+                    None => self.constant(Value::Unit),
+                };
+                self.jump_away(Terminator::Return(value));
+            }
+            ast::Stmt::Assign(assign) => {
+                let value = self.expr(assign.value);
+                let dest = self.lookup(&assign.name.name);
+                self.builder.push(Inst::Copy { dest, src: value });
+            }
+            ast::Stmt::Expr(expr) => {
+                self.expr(*expr);
+            }
+        }
+    }
+    fn lookup(&self, name: &str) -> Local {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name).copied())
+            .unwrap()
+    }
+    fn expr(&mut self, id: Id<ast::Expr>) -> Local {
+        match &self.ast.expr(id).kind {
+            // typeck checked that the literal fits its type
+            ast::ExprKind::Literal(ast::Literal::Int(digits)) => {
+                self.constant(Value::Int(digits.parse().unwrap()))
+            }
+            ast::ExprKind::Literal(ast::Literal::Bool(v)) => self.constant(Value::Bool(*v)),
+            // the value at this point, not after later assignments
+            ast::ExprKind::Var(name) => {
+                let var = self.lookup(name);
+                self.copy(var)
+            }
+            ast::ExprKind::Binary { op, lhs, rhs } => {
+                let bits = match self.types.ty(*lhs) {
+                    Type::Int(bits) => bits,
+                    _ => 64,
+                };
+                let lhs = self.expr(*lhs);
+                let rhs = self.expr(*rhs);
+                let op = match op {
+                    ast::BinOp::Add => neco_ir::BinOp::Add,
+                    ast::BinOp::Sub => neco_ir::BinOp::Sub,
+                    ast::BinOp::Mul => neco_ir::BinOp::Mul,
+                    ast::BinOp::Div => neco_ir::BinOp::Div,
+                    ast::BinOp::Eq => neco_ir::BinOp::Eq,
+                    ast::BinOp::Ne => neco_ir::BinOp::Ne,
+                    ast::BinOp::Lt => neco_ir::BinOp::Lt,
+                    ast::BinOp::Le => neco_ir::BinOp::Le,
+                    ast::BinOp::Gt => neco_ir::BinOp::Gt,
+                    ast::BinOp::Ge => neco_ir::BinOp::Ge,
+                };
+                let dest = self.builder.new_local();
+                self.builder.push(Inst::Binary {
+                    dest,
+                    op,
+                    bits,
+                    lhs,
+                    rhs,
+                });
+                dest
+            }
+            ast::ExprKind::Call { callee, args } => {
+                let args = args.iter().map(|arg| self.expr(*arg)).collect();
+                let dest = self.builder.new_local();
+                self.builder.push(Inst::Call {
+                    dest,
+                    function: self.fns[&callee.name],
+                    args,
+                });
+                dest
+            }
+            ast::ExprKind::Block(block) => self.block(block),
+            ast::ExprKind::If { cond, then, else_ } => {
+                let cond = self.expr(*cond);
+                let then_block = self.builder.new_block();
+                let else_block = self.builder.new_block();
+                let join = self.builder.new_block();
+                let res = self.builder.new_local();
+                self.builder.terminate(Terminator::Branch {
+                    cond,
+                    then: then_block,
+                    else_: else_block,
+                });
+                self.builder.switch_to(then_block);
+                let value = self.block(then);
+                self.builder.push(Inst::Copy {
+                    dest: res,
+                    src: value,
+                });
+                self.builder.terminate(Terminator::Jump(join));
+                self.builder.switch_to(else_block);
+                let value = match else_ {
+                    Some(else_) => self.expr(*else_),
+                    None => self.constant(Value::Unit),
+                };
+                self.builder.push(Inst::Copy {
+                    dest: res,
+                    src: value,
+                });
+                self.builder.terminate(Terminator::Jump(join));
+                self.builder.switch_to(join);
+                res
+            }
+            ast::ExprKind::While { cond, body } => {
+                let header = self.builder.new_block();
+                let body_block = self.builder.new_block();
+                let exit = self.builder.new_block();
+                self.builder.terminate(Terminator::Jump(header));
+                self.builder.switch_to(header);
+                let cond = self.expr(*cond);
+                self.builder.terminate(Terminator::Branch {
+                    cond,
+                    then: body_block,
+                    else_: exit,
+                });
+                self.builder.switch_to(body_block);
+                self.loop_body(header, exit, None, body);
+                self.builder.switch_to(exit);
+                self.constant(Value::Unit)
+            }
+            ast::ExprKind::Loop(body) => {
+                let body_block = self.builder.new_block();
+                let exit = self.builder.new_block();
+                let res = self.builder.new_local();
+                self.builder.terminate(Terminator::Jump(body_block));
+                self.builder.switch_to(body_block);
+                self.loop_body(body_block, exit, Some(res), body);
+                self.builder.switch_to(exit);
+                res
+            }
+            ast::ExprKind::Break(value) => {
+                let value = value.map(|value| self.expr(value));
+                let target = self.loops.last().unwrap();
+                let break_ = target.break_;
+                if let (Some(dest), Some(src)) = (target.value, value) {
+                    self.builder.push(Inst::Copy { dest, src });
+                }
+                self.jump_away(Terminator::Jump(break_));
+                // never read
+                self.builder.new_local()
+            }
+            ast::ExprKind::Continue => {
+                let continue_ = self.loops.last().unwrap().continue_;
+                self.jump_away(Terminator::Jump(continue_));
+                self.builder.new_local()
+            }
+        }
+    }
+    // the body jumps back to continue_ at its end
+    fn loop_body(
+        &mut self,
+        continue_: BlockId,
+        break_: BlockId,
+        value: Option<Local>,
+        body: &ast::Block,
+    ) {
+        self.loops.push(LoopTarget {
+            continue_,
+            break_,
+            value,
+        });
+        self.block(body);
+        self.loops.pop();
+        self.builder.terminate(Terminator::Jump(continue_));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compile;
+    use neco_ir::{BlockId, Inst, Local, Terminator};
+    use neco_syn::Project;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_lower_return() {
+        let mut project = Project::new();
+        let file_id = project.add_file(
+            PathBuf::from("test.felis"),
+            "fn f(x: i32) -> i32 { return x; 1 }".chars().collect(),
+        );
+        let (program, diagnostics) = compile(project.get_file(file_id).unwrap());
+        assert!(diagnostics.is_empty());
+        let f = &program.unwrap().functions[0];
+        assert_eq!(f.params, 1);
+        // the code after return is in a block of its own
+        assert_eq!(f.blocks.len(), 2);
+        assert_eq!(
+            f.blocks[0].insts,
+            vec![Inst::Copy {
+                dest: Local(1),
+                src: Local(0)
+            }]
+        );
+        assert_eq!(f.blocks[0].terminator, Terminator::Return(Local(1)));
+        assert!(matches!(f.blocks[1].insts[0], Inst::Const { .. }));
+    }
+
+    #[test]
+    fn test_lower_while() {
+        let mut project = Project::new();
+        let file_id = project.add_file(
+            PathBuf::from("test.felis"),
+            "fn f(x: i32) { while x < 10 { x = x + 1; } }"
+                .chars()
+                .collect(),
+        );
+        let (program, diagnostics) = compile(project.get_file(file_id).unwrap());
+        assert!(diagnostics.is_empty());
+        let f = &program.unwrap().functions[0];
+        // entry, header, body, exit
+        assert_eq!(f.blocks[0].terminator, Terminator::Jump(BlockId(1)));
+        assert!(matches!(
+            f.blocks[1].terminator,
+            Terminator::Branch {
+                then: BlockId(2),
+                else_: BlockId(3),
+                ..
+            }
+        ));
+        assert_eq!(f.blocks[2].terminator, Terminator::Jump(BlockId(1)));
+        assert!(matches!(f.blocks[3].terminator, Terminator::Return(_)));
+    }
+}
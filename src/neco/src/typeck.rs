@@ -0,0 +1,661 @@
+// type checking of the Felis AST with the annotator of neco-types-and-values.
+//
+// every expression gets an annotation, and the relations between them are given to the
+// annotator. the mismatches it finds become diagnostics at the spans of the annotations.
+
+use neco_syn::{
+    diagnostic::Diagnostic,
+    lang::felis::ast::{self, Ast},
+    Span,
+};
+use neco_table::{Id, SubTable};
+use neco_types_and_values::{AnnotationId, Annotator, Type, TypeError};
+use std::collections::HashMap;
+
+pub struct Types {
+    pub annotator: Annotator,
+    pub exprs: SubTable<ast::Expr, AnnotationId>,
+}
+
+impl Types {
+    // the type of an expression after annotate
+    pub fn ty(&self, id: Id<ast::Expr>) -> Type {
+        self.annotator.get_ty(*self.exprs.get(id).unwrap())
+    }
+}
+
+struct Signature {
+    params: Vec<AnnotationId>,
+    ret: AnnotationId,
+}
+
+struct Checker<'a> {
+    ast: &'a Ast,
+    annotator: Annotator,
+    exprs: SubTable<ast::Expr, AnnotationId>,
+    fns: HashMap<String, Signature>,
+    // the innermost scope is the last
+    scopes: Vec<HashMap<String, AnnotationId>>,
+    // the return type of the current function
+    ret: Option<AnnotationId>,
+    // the types of the values of the enclosing loops, the innermost last
+    loops: Vec<AnnotationId>,
+    // integer literals, checked against their types after annotate
+    literals: Vec<(AnnotationId, &'a str, Span)>,
+    // the annotations which make the operands of an operator integers, with the kind of the operator
+    operands: HashMap<AnnotationId, &'static str>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+pub fn typeck(ast: &Ast) -> (Types, Vec<Diagnostic>) {
+    let mut checker = Checker {
+        ast,
+        annotator: Annotator::new(),
+        exprs: SubTable::new(),
+        fns: HashMap::new(),
+        scopes: vec![],
+        ret: None,
+        loops: vec![],
+        literals: vec![],
+        operands: HashMap::new(),
+        diagnostics: vec![],
+    };
+    // functions can be called before their definitions
+    for id in &ast.root {
+        match &ast.item(*id).kind {
+            ast::ItemKind::Fn(fn_def) => checker.signature(fn_def),
+        }
+    }
+    for id in &ast.root {
+        match &ast.item(*id).kind {
+            ast::ItemKind::Fn(fn_def) => checker.fn_def(fn_def),
+        }
+    }
+    let mut diagnostics = checker.diagnostics;
+    if let Err(errors) = checker.annotator.annotate(Type::Int(32)) {
+        for error in errors {
+            match error {
+                TypeError::Mismatch {
+                    expected,
+                    found_ty,
+                    found_span,
+                    ..
+                } if checker.operands.contains_key(&expected) => {
+                    diagnostics.push(
+                        Diagnostic::error(
+                            format!(
+                                "{} operands must be integers, found `{}`",
+                                checker.operands[&expected],
+                                type_name(&found_ty)
+                            ),
+                            found_span,
+                        )
+                        .with_code("E0003"),
+                    );
+                }
+                TypeError::Mismatch {
+                    expected_ty,
+                    found_ty,
+                    expected_span: expected,
+                    found_span: found,
+                    ..
+                } => {
+                    let mut diagnostic = Diagnostic::error(
+                        format!(
+                            "mismatched types: expected `{}`, found `{}`",
+                            type_name(&expected_ty),
+                            type_name(&found_ty)
+                        ),
+                        found,
+                    )
+                    .with_code("E0003");
+                    if expected != found {
+                        diagnostic = diagnostic.with_label(
+                            expected,
+                            format!("expected `{}` because of this", type_name(&expected_ty)),
+                        );
+                    }
+                    diagnostics.push(diagnostic);
+                }
+            }
+        }
+    }
+    for (ann, digits, span) in checker.literals {
+        if let Type::Int(bits) = checker.annotator.get_ty(ann) {
+            if !fits(digits, bits) {
+                diagnostics.push(
+                    Diagnostic::error(
+                        format!("integer literal is out of range for `i{}`", bits),
+                        span,
+                    )
+                    .with_code("E0007"),
+                );
+            }
+        }
+    }
+    let types = Types {
+        annotator: checker.annotator,
+        exprs: checker.exprs,
+    };
+    (types, diagnostics)
+}
+
+// whether the digits of a literal fit a signed integer of bits
+fn fits(digits: &str, bits: usize) -> bool {
+    digits
+        .parse::<u64>()
+        .is_ok_and(|v| bits > 0 && bits <= 64 && v < 1 << (bits - 1))
+}
+
+// the name of a type as written in Felis
+pub fn type_name(ty: &Type) -> String {
+    match ty {
+        Type::Unit => "()".to_string(),
+        Type::Bool => "bool".to_string(),
+        Type::Int(bits) => format!("i{}", bits),
+        Type::Infer => "_".to_string(),
+        Type::InferInteger => "{integer}".to_string(),
+    }
+}
+
+impl<'a> Checker<'a> {
+    fn annotation(&mut self, ty: Type, span: Span) -> AnnotationId {
+        self.annotator.create_annotation(ty, span)
+    }
+    fn type_expr(&mut self, ty: &ast::TypeExpr) -> AnnotationId {
+        let t = match &ty.kind {
+            ast::TypeExprKind::Named(name) => match name.as_str() {
+                "bool" => Type::Bool,
+                "i32" => Type::Int(32),
+                "i64" => Type::Int(64),
+                _ => {
+                    self.diagnostics.push(
+                        Diagnostic::error(format!("cannot find type `{}`", name), ty.span)
+                            .with_code("E0005"),
+                    );
+                    Type::Infer
+                }
+            },
+        };
+        self.annotation(t, ty.span)
+    }
+    fn signature(&mut self, fn_def: &ast::FnDef) {
+        let params = fn_def
+            .params
+            .iter()
+            .map(|param| self.type_expr(&param.ty))
+            .collect();
+        let ret = match &fn_def.ret {
+            Some(ty) => self.type_expr(ty),
+            None => self.annotation(Type::Unit, fn_def.name.span),
+        };
+        if self.fns.contains_key(&fn_def.name.name) {
+            self.diagnostics.push(
+                Diagnostic::error(
+                    format!("the name `{}` is defined multiple times", fn_def.name.name),
+                    fn_def.name.span,
+                )
+                .with_code("E0006"),
+            );
+            return;
+        }
+        self.fns
+            .insert(fn_def.name.name.clone(), Signature { params, ret });
+    }
+    fn fn_def(&mut self, fn_def: &ast::FnDef) {
+        let sig = &self.fns[&fn_def.name.name];
+        let ret = sig.ret;
+        let params = sig.params.clone();
+        let mut scope = HashMap::new();
+        for (param, ann) in fn_def.params.iter().zip(params) {
+            scope.insert(param.name.name.clone(), ann);
+        }
+        self.scopes.push(scope);
+        self.ret = Some(ret);
+        let body = self.block(&fn_def.body);
+        self.annotator.same(&[ret, body]);
+        self.ret = None;
+        self.scopes.pop();
+    }
+    fn block(&mut self, block: &ast::Block) -> AnnotationId {
+        self.scopes.push(HashMap::new());
+        for stmt in &block.stmts {
+            self.stmt(stmt);
+        }
+        let res = match block.tail {
+            Some(tail) => self.expr(tail),
+            // a block which ends with `return` or `break` has no value of its own
+            None if block.stmts.last().is_some_and(|stmt| self.diverges(stmt)) => {
+                self.annotation(Type::Infer, block.span)
+            }
+            None => self.annotation(Type::Unit, block.span),
+        };
+        self.scopes.pop();
+        res
+    }
+    fn diverges(&self, stmt: &ast::Stmt) -> bool {
+        match stmt {
+            ast::Stmt::Return(_) => true,
+            ast::Stmt::Expr(expr) => matches!(
+                self.ast.expr(*expr).kind,
+                ast::ExprKind::Break(_) | ast::ExprKind::Continue
+            ),
+            _ => false,
+        }
+    }
+    fn stmt(&mut self, stmt: &ast::Stmt) {
+        match stmt {
+            ast::Stmt::Let(let_) => {
+                let init = self.expr(let_.init);
+                let ann = match &let_.ty {
+                    Some(ty) => {
+                        let ann = self.type_expr(ty);
+                        self.annotator.same(&[ann, init]);
+                        ann
+                    }
+                    None => init,
+                };
+                self.scopes
+                    .last_mut()
+                    .unwrap()
+                    .insert(let_.name.name.clone(), ann);
+            }
+            ast::Stmt::Return(return_) => {
+                let value = match return_.value {
+                    Some(value) => self.expr(value),
+                    None => self.annotation(Type::Unit, return_.span),
+                };
+                let ret = self.ret.unwrap();
+                self.annotator.same(&[ret, value]);
+            }
+            ast::Stmt::Assign(assign) => {
+                let value = self.expr(assign.value);
+                match self.lookup(&assign.name.name) {
+                    Some(var) => self.annotator.same(&[var, value]),
+                    None => self.not_found(&assign.name.name, assign.name.span),
+                }
+            }
+            ast::Stmt::Expr(expr) => {
+                self.expr(*expr);
+            }
+        }
+    }
+    fn not_found(&mut self, name: &str, span: Span) {
+        self.diagnostics.push(
+            Diagnostic::error(format!("cannot find value `{}` in this scope", name), span)
+                .with_code("E0004"),
+        );
+    }
+    // conditions are bool
+    fn cond(&mut self, cond: Id<ast::Expr>) {
+        let cond = self.expr(cond);
+        let bool_ = self.annotation(Type::Bool, self.annotator.get_span(cond));
+        self.annotator.same(&[bool_, cond]);
+    }
+    fn loop_body(&mut self, ty: AnnotationId, body: &ast::Block) {
+        self.loops.push(ty);
+        let body_ty = self.block(body);
+        self.loops.pop();
+        // the value of a loop is given by break
+        let unit = self.annotation(Type::Unit, body.span);
+        self.annotator.same(&[unit, body_ty]);
+    }
+    fn outside_of_loop(&mut self, keyword: &str, span: Span) -> bool {
+        if self.loops.is_empty() {
+            self.diagnostics.push(
+                Diagnostic::error(format!("`{}` outside of a loop", keyword), span)
+                    .with_code("E0009"),
+            );
+            return true;
+        }
+        false
+    }
+    fn lookup(&self, name: &str) -> Option<AnnotationId> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name).copied())
+    }
+    fn expr(&mut self, id: Id<ast::Expr>) -> AnnotationId {
+        let expr = self.ast.expr(id);
+        let span = expr.span;
+        let res = match &expr.kind {
+            ast::ExprKind::Literal(ast::Literal::Int(digits)) => {
+                let res = self.annotation(Type::InferInteger, span);
+                self.literals.push((res, digits, span));
+                res
+            }
+            ast::ExprKind::Literal(ast::Literal::Bool(_)) => self.annotation(Type::Bool, span),
+            ast::ExprKind::Var(name) => {
+                let res = self.annotation(Type::Infer, span);
+                match self.lookup(name) {
+                    Some(var) => self.annotator.same(&[var, res]),
+                    None => self.not_found(name, span),
+                }
+                res
+            }
+            ast::ExprKind::Binary { op, lhs, rhs } => {
+                let lhs = self.expr(*lhs);
+                let rhs = self.expr(*rhs);
+                self.annotator.same(&[lhs, rhs]);
+                // the operands are integers, except for == and !=
+                let kind = match op {
+                    ast::BinOp::Eq | ast::BinOp::Ne => None,
+                    ast::BinOp::Add | ast::BinOp::Sub | ast::BinOp::Mul | ast::BinOp::Div => {
+                        Some("arithmetic")
+                    }
+                    ast::BinOp::Lt | ast::BinOp::Le | ast::BinOp::Gt | ast::BinOp::Ge => {
+                        Some("comparison")
+                    }
+                };
+                if let Some(kind) = kind {
+                    let integer = self.annotation(Type::InferInteger, self.annotator.get_span(lhs));
+                    self.operands.insert(integer, kind);
+                    self.annotator.same(&[integer, lhs]);
+                }
+                match op {
+                    ast::BinOp::Add | ast::BinOp::Sub | ast::BinOp::Mul | ast::BinOp::Div => {
+                        let res = self.annotation(Type::Infer, span);
+                        self.annotator.same(&[lhs, res]);
+                        res
+                    }
+                    _ => self.annotation(Type::Bool, span),
+                }
+            }
+            ast::ExprKind::Call { callee, args } => {
+                let args: Vec<_> = args.iter().map(|arg| self.expr(*arg)).collect();
+                let res = self.annotation(Type::Infer, span);
+                match self.fns.get(&callee.name) {
+                    Some(sig) => {
+                        let ret = sig.ret;
+                        let params = sig.params.clone();
+                        if params.len() != args.len() {
+                            self.diagnostics.push(
+                                Diagnostic::error(
+                                    format!(
+                                        "this function takes {} arguments but {} were supplied",
+                                        params.len(),
+                                        args.len()
+                                    ),
+                                    span,
+                                )
+                                .with_code("E0008"),
+                            );
+                        }
+                        for (param, arg) in params.into_iter().zip(args) {
+                            self.annotator.same(&[param, arg]);
+                        }
+                        self.annotator.same(&[ret, res]);
+                    }
+                    None => self.diagnostics.push(
+                        Diagnostic::error(
+                            format!("cannot find function `{}` in this scope", callee.name),
+                            callee.span,
+                        )
+                        .with_code("E0004"),
+                    ),
+                }
+                res
+            }
+            ast::ExprKind::Block(block) => self.block(block),
+            ast::ExprKind::If { cond, then, else_ } => {
+                self.cond(*cond);
+                let then = self.block(then);
+                match else_ {
+                    Some(else_) => {
+                        let else_ = self.expr(*else_);
+                        self.annotator.same(&[then, else_]);
+                        let res = self.annotation(Type::Infer, span);
+                        self.annotator.same(&[then, res]);
+                        res
+                    }
+                    // an if without else has no value
+                    None => {
+                        let res = self.annotation(Type::Unit, span);
+                        self.annotator.same(&[res, then]);
+                        res
+                    }
+                }
+            }
+            ast::ExprKind::While { cond, body } => {
+                self.cond(*cond);
+                // break in while has no value
+                let res = self.annotation(Type::Unit, span);
+                self.loop_body(res, body);
+                res
+            }
+            ast::ExprKind::Loop(body) => {
+                let res = self.annotation(Type::Infer, span);
+                self.loop_body(res, body);
+                res
+            }
+            ast::ExprKind::Break(value) => {
+                let value = match value {
+                    Some(value) => self.expr(*value),
+                    None => self.annotation(Type::Unit, span),
+                };
+                if !self.outside_of_loop("break", span) {
+                    let ty = *self.loops.last().unwrap();
+                    self.annotator.same(&[ty, value]);
+                }
+                self.annotation(Type::Infer, span)
+            }
+            ast::ExprKind::Continue => {
+                self.outside_of_loop("continue", span);
+                self.annotation(Type::Infer, span)
+            }
+        };
+        self.exprs.insert(id, res);
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use neco_syn::{lang::felis, Project};
+    use std::path::PathBuf;
+
+    fn typeck_str(s: &str) -> (Project, Ast, Types, Vec<Diagnostic>) {
+        let mut project = Project::new();
+        let file_id = project.add_file(PathBuf::from("test.felis"), s.chars().collect());
+        let file = felis::parse(project.get_file(file_id).unwrap()).0.unwrap();
+        let ast = felis::lower::lower(&file);
+        let (types, diagnostics) = typeck(&ast);
+        (project, ast, types, diagnostics)
+    }
+
+    fn body(ast: &Ast, i: usize) -> &ast::Block {
+        match &ast.item(ast.root[i]).kind {
+            ast::ItemKind::Fn(fn_def) => &fn_def.body,
+        }
+    }
+
+    #[test]
+    fn test_typeck() {
+        let (_, ast, types, diagnostics) = typeck_str(
+            "fn main() -> i64 { let x = 1; let y = f(x, true); y }\n\
+             fn f(a: i64, b: bool) -> i64 { return a * 2; }",
+        );
+        assert!(diagnostics.is_empty());
+        let main = body(&ast, 0);
+        assert_eq!(types.ty(main.tail.unwrap()), Type::Int(64));
+        match &main.stmts[0] {
+            ast::Stmt::Let(let_) => assert_eq!(types.ty(let_.init), Type::Int(64)),
+            _ => panic!(),
+        }
+        // integers default to i32
+        let (_, ast, types, diagnostics) = typeck_str("fn g() { 1 + 2; }");
+        assert!(diagnostics.is_empty());
+        match &body(&ast, 0).stmts[0] {
+            ast::Stmt::Expr(expr) => assert_eq!(types.ty(*expr), Type::Int(32)),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_typeck_mismatch() {
+        let (project, _, _, diagnostics) =
+            typeck_str("fn f(x: i32) -> bool { x }\nfn g() { f(true); }");
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].code.as_deref(), Some("E0003"));
+        assert_eq!(
+            diagnostics[0].message,
+            "mismatched types: expected `bool`, found `i32`"
+        );
+        assert_eq!(project.snippet(diagnostics[0].span).unwrap(), "x");
+        assert_eq!(
+            project.snippet(diagnostics[0].labels[0].span).unwrap(),
+            "bool"
+        );
+        assert_eq!(
+            diagnostics[1].message,
+            "mismatched types: expected `i32`, found `bool`"
+        );
+        assert_eq!(project.snippet(diagnostics[1].span).unwrap(), "true");
+
+        let (project, _, _, diagnostics) = typeck_str("fn f() -> i32 { return; }");
+        assert_eq!(
+            diagnostics[0].message,
+            "mismatched types: expected `i32`, found `()`"
+        );
+        assert_eq!(project.snippet(diagnostics[0].span).unwrap(), "return;");
+    }
+
+    #[test]
+    fn test_typeck_operands() {
+        let (project, _, _, diagnostics) =
+            typeck_str("fn f() -> bool { true < false }\nfn g() { let x = false * false; }");
+        let messages: Vec<_> = diagnostics
+            .iter()
+            .map(|d| (d.message.as_str(), project.snippet(d.span).unwrap()))
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                (
+                    "comparison operands must be integers, found `bool`",
+                    "true".to_string()
+                ),
+                (
+                    "arithmetic operands must be integers, found `bool`",
+                    "false".to_string()
+                ),
+            ]
+        );
+        assert!(diagnostics.iter().all(|d| d.labels.is_empty()));
+    }
+
+    #[test]
+    fn test_typeck_control_flow() {
+        let (_, ast, types, diagnostics) = typeck_str(
+            "fn f(n: i64) -> i64 {\n\
+             let x = loop { if n < 0 { break 1; } else { continue; } };\n\
+             while x == n { n = n - 1; }\n\
+             if true { x } else { 0 }\n\
+             }",
+        );
+        assert!(diagnostics.is_empty());
+        let body = body(&ast, 0);
+        match &body.stmts[0] {
+            ast::Stmt::Let(let_) => assert_eq!(types.ty(let_.init), Type::Int(64)),
+            _ => panic!(),
+        }
+        match &body.stmts[1] {
+            ast::Stmt::Expr(expr) => assert_eq!(types.ty(*expr), Type::Unit),
+            _ => panic!(),
+        }
+        assert_eq!(types.ty(body.tail.unwrap()), Type::Int(64));
+    }
+
+    #[test]
+    fn test_typeck_control_flow_errors() {
+        let (project, _, _, diagnostics) = typeck_str(
+            "fn f() {\n\
+             if 1 { }\n\
+             let x = if true { 1 } else { false };\n\
+             while true { break 1; }\n\
+             if true { 1 }\n\
+             }\n\
+             fn g() { break; continue; }",
+        );
+        let messages: Vec<_> = diagnostics
+            .iter()
+            .map(|d| (d.message.as_str(), project.snippet(d.span).unwrap()))
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                ("`break` outside of a loop", "break".to_string()),
+                ("`continue` outside of a loop", "continue".to_string()),
+                (
+                    "mismatched types: expected `bool`, found `i32`",
+                    "1".to_string()
+                ),
+                (
+                    "mismatched types: expected `i32`, found `bool`",
+                    "false".to_string()
+                ),
+                (
+                    "mismatched types: expected `()`, found `i32`",
+                    "1".to_string()
+                ),
+                (
+                    "mismatched types: expected `()`, found `i32`",
+                    "1".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_typeck_literal_range() {
+        let (project, _, _, diagnostics) = typeck_str(
+            "fn f() -> i32 { let x: i32 = 3000000000; let y: i64 = 3000000000; 2147483647 }\n\
+             fn g() { 9223372036854775808; 99999999999999999999; }",
+        );
+        let messages: Vec<_> = diagnostics
+            .iter()
+            .map(|d| (d.message.as_str(), project.snippet(d.span).unwrap()))
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                (
+                    "integer literal is out of range for `i32`",
+                    "3000000000".to_string()
+                ),
+                (
+                    "integer literal is out of range for `i32`",
+                    "9223372036854775808".to_string()
+                ),
+                (
+                    "integer literal is out of range for `i32`",
+                    "99999999999999999999".to_string()
+                ),
+            ]
+        );
+        assert!(fits("9223372036854775807", 64));
+        assert!(!fits("9223372036854775808", 64));
+        assert!(fits("127", 8) && !fits("128", 8));
+    }
+
+    #[test]
+    fn test_typeck_names() {
+        let (project, _, _, diagnostics) = typeck_str("fn f(x: u8) { y; g(); f(); }\nfn f() {}");
+        let messages: Vec<_> = diagnostics
+            .iter()
+            .map(|d| (d.code.as_deref().unwrap(), project.snippet(d.span).unwrap()))
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                ("E0005", "u8".to_string()),
+                ("E0006", "f".to_string()),
+                ("E0004", "y".to_string()),
+                ("E0004", "g".to_string()),
+                ("E0008", "f()".to_string()),
+            ]
+        );
+    }
+}
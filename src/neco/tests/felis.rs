@@ -0,0 +1,44 @@
+// runs the programs in tests/Felis. the first line of a program is either `// expected: <value>`,
+// the printed value of its main, or `// error: <code> <code> ...`, the codes of all the
+// diagnostics it must report, in order.
+// scratch files named 999* are skipped.
+
+use neco::ErrorFormat;
+use neco_syn::Project;
+use std::path::Path;
+
+#[test]
+fn test_felis_programs() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../tests/Felis");
+    let mut project = Project::new();
+    project.load_dir(&dir).unwrap();
+    let mut count = 0;
+    for file in project.files() {
+        let name = file.path().file_name().unwrap().to_string_lossy();
+        if name.starts_with("999") {
+            continue;
+        }
+        count += 1;
+        let body: String = file.body().iter().collect();
+        let first_line = body.lines().next().unwrap_or("");
+        let (program, diagnostics) = neco::compile(file);
+        let rendered = neco::emit(&project, &diagnostics, ErrorFormat::Human);
+        if let Some(expected) = first_line.strip_prefix("// expected: ") {
+            assert!(diagnostics.is_empty(), "{}:\n{}", name, rendered);
+            let value = neco::run(&program.unwrap());
+            assert_eq!(
+                value.map(|v| v.to_string()).as_deref(),
+                Ok(expected),
+                "{}",
+                name
+            );
+        } else if let Some(codes) = first_line.strip_prefix("// error: ") {
+            let codes: Vec<_> = codes.split_whitespace().map(Some).collect();
+            let found: Vec<_> = diagnostics.iter().map(|d| d.code.as_deref()).collect();
+            assert_eq!(found, codes, "{}:\n{}", name, rendered);
+        } else {
+            panic!("{}: no expectation on the first line", name);
+        }
+    }
+    assert!(count > 0);
+}